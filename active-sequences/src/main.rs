@@ -1,4 +1,5 @@
 use std::error::Error;
+use std::io::{BufRead, Write};
 use std::path::PathBuf;
 
 extern crate log;
@@ -7,13 +8,12 @@ use bio::io::fasta;
 
 use structopt::StructOpt;
 
+use rnc_core::containers::bloom::{BloomStore, Verified};
+use rnc_core::containers::table::{self, UrsTaxidTable};
+use rnc_core::containers::urs_taxid::{load_store, UrsTaxidStore};
 use rnc_core::json_sequence::{each_sequence, Sequence};
 use rnc_core::urs_taxid::UrsTaxid;
 
-pub mod container;
-
-use crate::container::UrsTaxidContainer;
-
 /// This is a command to process a list of active urs_taxids and urs sequences and produce a
 /// fasta file of the active urs taxids. The sequence file only needs to contain an entry for each
 /// urs and the urs_taxid file may contain duplicates.
@@ -21,7 +21,7 @@ use crate::container::UrsTaxidContainer;
 #[structopt(rename_all = "kebab-case")]
 struct Opt {
     /// A file where each line is a urs_taxid, which are all active xrefs that need to be output.
-    /// This may contain duplicates.
+    /// This may contain duplicates. This may also be a table of urs_taxids built by urs-table.
     #[structopt(parse(from_os_str))]
     xref_urs_taxids: PathBuf,
 
@@ -32,20 +32,63 @@ struct Opt {
     /// File to output to, '-' means stdout.
     #[structopt(parse(from_os_str))]
     output: PathBuf,
+
+    /// A Bloom filter of the URS in xref_urs_taxids, built by `urs-table bloom`. Sequences the
+    /// filter rejects are skipped without checking xref_urs_taxids. This requires
+    /// xref_urs_taxids to be a table, so the exact set is never loaded into memory.
+    #[structopt(long, parse(from_os_str))]
+    bloom_filter: Option<PathBuf>,
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let opt = Opt::from_args();
-    let input = rnc_utils::buf_reader(&opt.filename)?;
-    let output = rnc_utils::buf_writer(&opt.output)?;
+fn write_active<W: Write>(
+    input: Box<dyn BufRead>,
+    output: W,
+    store: &dyn UrsTaxidStore,
+) -> Result<(), Box<dyn Error>> {
     let mut writer = fasta::Writer::new(output);
-
-    let container = UrsTaxidContainer::from_path(&opt.xref_urs_taxids)?;
     each_sequence(input, |sequence: Sequence| {
-        let urs_taxid: UrsTaxid = sequence.id.parse()?;
-        if container.contains(&urs_taxid) {
+        let urs_taxid = UrsTaxid::parse_any_case(sequence.id)?;
+        if store.contains(&urs_taxid) {
             writer.write_record(&sequence.into())?;
         }
         Ok(())
-    })
+    })?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let opt = Opt::from_args();
+    let input = rnc_utils::buf_reader(&opt.filename)?;
+    let mut output = rnc_utils::buf_writer(&opt.output)?;
+
+    match &opt.bloom_filter {
+        None => {
+            let container = load_store(&opt.xref_urs_taxids)?;
+            write_active(input, &mut output, &container)?
+        },
+        Some(path) => {
+            // Loading a text file would hold every URS_taxid in memory, which is what the filter
+            // is meant to avoid, so hits are only ever confirmed against a mapped table.
+            if !table::is_table(&opt.xref_urs_taxids)? {
+                return Err(format!(
+                    "--bloom-filter requires xref_urs_taxids to be a table built by urs-table, {} \
+                     is not",
+                    opt.xref_urs_taxids.display()
+                )
+                .into());
+            }
+            let exact = UrsTaxidTable::open(&opt.xref_urs_taxids)?;
+            let filter = BloomStore::read_from(rnc_utils::buf_reader(path)?)?;
+            let verified = Verified::new(filter, exact);
+            write_active(input, &mut output, &verified)?;
+            log::info!(
+                "Bloom filter accepted {} sequences, {} of which were not active",
+                verified.positives(),
+                verified.false_positives()
+            );
+        },
+    }
+    output.finish()?;
+    Ok(())
 }
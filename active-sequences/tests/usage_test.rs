@@ -0,0 +1,69 @@
+use std::io;
+use std::path::Path;
+use std::process::Output;
+
+use tempfile::NamedTempFile;
+
+use rnc_core::containers::{bloom::BloomStore, table, urs::UrsStore};
+
+use rnc_test_utils::temp_file_with;
+
+fn active(filter: &Path, xrefs: &Path, sequences: &Path) -> io::Result<Output> {
+    test_bin::get_test_bin("active-sequences")
+        .arg("--bloom-filter")
+        .arg(filter)
+        .arg(xrefs)
+        .arg(sequences)
+        .arg("-")
+        .output()
+}
+
+fn sequences() -> io::Result<NamedTempFile> {
+    temp_file_with(vec![
+        r#"{"id": "URS0000762A36_9606", "description": "active", "sequence": "ACGU"}"#,
+        r#"{"id": "URS0000762A36_562", "description": "other taxid", "sequence": "ACGU"}"#,
+        r#"{"id": "URS0000000002_9606", "description": "inactive", "sequence": "GGGG"}"#,
+    ])
+}
+
+fn bloom_filter() -> Result<NamedTempFile, Box<dyn std::error::Error>> {
+    let mut filter = BloomStore::new(2, 0.01)?;
+    filter.add(&"URS0000762A36".parse()?);
+    filter.add(&"URS0000000001".parse()?);
+    let file = NamedTempFile::new()?;
+    filter.write_to(file.as_file())?;
+    Ok(file)
+}
+
+#[test]
+fn filtering_with_a_bloom_filter_test() -> Result<(), Box<dyn std::error::Error>> {
+    let table_file = NamedTempFile::new()?;
+    table::write_urs_taxids(table_file.as_file(), vec![
+        "URS0000762A36_9606".parse()?,
+        "URS0000000001_9606".parse()?,
+    ])?;
+    let filter = bloom_filter()?;
+    let sequences = sequences()?;
+
+    let result = active(filter.path(), table_file.path(), sequences.path())?;
+    assert_eq!(String::from_utf8_lossy(&result.stderr), "");
+    assert_eq!(result.status.success(), true);
+    assert_eq!(String::from_utf8_lossy(&result.stdout), ">URS0000762A36_9606 active\nACGU\n");
+
+    Ok(())
+}
+
+#[test]
+fn bloom_filters_require_a_table_test() -> Result<(), Box<dyn std::error::Error>> {
+    let xrefs = temp_file_with(vec!["URS0000762A36_9606"])?;
+    let filter = bloom_filter()?;
+    let sequences = sequences()?;
+
+    let result = active(filter.path(), xrefs.path(), sequences.path())?;
+    assert_eq!(result.status.success(), false);
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(stderr.contains("requires xref_urs_taxids to be a table"), "{}", stderr);
+    assert_eq!(String::from_utf8_lossy(&result.stdout), "");
+
+    Ok(())
+}
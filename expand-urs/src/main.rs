@@ -1,4 +1,7 @@
-use std::path::PathBuf;
+use std::{
+    io::Write,
+    path::PathBuf,
+};
 
 use structopt::StructOpt;
 
@@ -8,7 +11,7 @@ use anyhow::{
 };
 
 use rnc_core::{
-    containers::urs_taxid::UrsTaxidMapping,
+    containers::urs_taxid::load_store,
     urs::Urs,
 };
 
@@ -20,7 +23,8 @@ use rnc_core::{
 #[structopt(rename_all = "kebab-case")]
 struct Opt {
     /// A file where each line is a urs_taxid, which are all urs_taxids that need to be
-    /// output. Duplicate will be treated as single entry.
+    /// output. Duplicate will be treated as single entry. This may also be a table of
+    /// urs_taxids built by urs-table.
     #[structopt(parse(from_os_str))]
     active_file: PathBuf,
 
@@ -40,7 +44,7 @@ fn main() -> Result<()> {
     let mut input = rnc_utils::buf_reader(&opt.filename)?;
     let mut output = rnc_utils::buf_writer(&opt.output)?;
 
-    let container = UrsTaxidMapping::from_urs_file(&opt.active_file)?;
+    let container = load_store(&opt.active_file)?;
     let mut buf = String::new();
     loop {
         match input.read_line(&mut buf)? {
@@ -52,8 +56,7 @@ fn main() -> Result<()> {
 
                 if let Some(m) = json.as_object_mut() {
                     if let Some(serde_json::Value::String(raw_urs)) = m.get("urs") {
-                        let urs: Urs = raw_urs
-                            .parse()
+                        let urs = Urs::parse_any_case(raw_urs)
                             .with_context(|| format!("Failed to parse URS id {}", &raw_urs))?;
                         let urs_taxids = container.urs_taxids(&urs);
                         if urs_taxids.len() == 0 {
@@ -76,5 +79,6 @@ fn main() -> Result<()> {
         }
     }
 
+    output.finish()?;
     Ok(())
 }
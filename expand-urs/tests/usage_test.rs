@@ -36,3 +36,30 @@ fn simple_expanding_test() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn expanding_with_a_table_test() -> Result<(), Box<dyn std::error::Error>> {
+    let table_file = tempfile::NamedTempFile::new()?;
+    rnc_core::containers::table::write_urs_taxids(table_file.as_file(), vec![
+        "URS0000762A36_9606".parse()?,
+        "URS0000762A36_562".parse()?,
+        "URS0000000001_9606".parse()?,
+    ])?;
+    let json_file = temp_file_with(vec![
+        r#"{"urs": "URS0000762A36", "value": "2"}"#,
+        r#"{"urs": "URS0000000002", "value": "3"}"#,
+    ])?;
+
+    let output = PathBuf::from("-");
+    let result = expand(table_file.path(), json_file.path(), &output)?;
+    assert_eq!(result.status.success(), true);
+    assert_eq!(
+        result.jsonl()?,
+        vec![
+        json!({"id": "URS0000762A36_562", "urs": "URS0000762A36", "value": "2"}),
+        json!({"id": "URS0000762A36_9606", "urs": "URS0000762A36", "value": "2"}),
+        ]
+    );
+
+    Ok(())
+}
@@ -30,12 +30,17 @@ struct Opt {
 fn main() -> Result<(), Box<dyn Error>> {
     let opt = Opt::from_args();
     let input = rnc_utils::buf_reader(&opt.raw)?;
-    let output = rnc_utils::buf_writer(&opt.output)?;
-    let mut writer = fasta::Writer::new(output);
-    each_sequence(input, |sequence: Sequence| {
-        if !opt.only_valid_easel || valid_sequence(&sequence.sequence) {
-            writer.write_record(&sequence.into())?;
-        }
-        Ok(())
-    })
+    let mut output = rnc_utils::buf_writer(&opt.output)?;
+    {
+        let mut writer = fasta::Writer::new(&mut output);
+        each_sequence(input, |sequence: Sequence| {
+            if !opt.only_valid_easel || valid_sequence(&sequence.sequence) {
+                writer.write_record(&sequence.into())?;
+            }
+            Ok(())
+        })?;
+        writer.flush()?;
+    }
+    output.finish()?;
+    Ok(())
 }
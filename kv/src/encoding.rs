@@ -0,0 +1,149 @@
+use std::{
+    convert::TryInto,
+    str,
+    str::FromStr,
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use serde_json::{
+    Deserializer,
+    Value,
+};
+
+use anyhow::{
+    anyhow,
+    Result,
+};
+
+use rocksdb::DBCompressionType;
+
+/// The layout of the values stored in an index. Values for a key are always built by appending
+/// each newly indexed document to the existing value, so each format must allow decoding a
+/// series of concatenated documents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// The original layout, each document is the raw JSON text. These are decoded by parsing
+    /// the concatenated text as a stream of JSON documents.
+    Json,
+    /// Each document is CBOR encoded and prefixed by its length as a 4 byte big endian
+    /// integer. This is smaller and much faster to decode than the JSON text.
+    Framed,
+}
+
+impl Format {
+    /// The format of an index with the given format version, as stored in the index metadata.
+    pub fn from_version(version: u32) -> Result<Self> {
+        match version {
+            1 => Ok(Format::Json),
+            2 => Ok(Format::Framed),
+            _ => Err(anyhow!("Unsupported index format version {}", version)),
+        }
+    }
+
+    pub fn version(&self) -> u32 {
+        match self {
+            Format::Json => 1,
+            Format::Framed => 2,
+        }
+    }
+
+    /// Encode a single document. The raw text must be the JSON text of the given document.
+    pub fn encode(&self, raw: &str, document: &Value) -> Result<Vec<u8>> {
+        match self {
+            Format::Json => Ok(raw.as_bytes().to_vec()),
+            Format::Framed => {
+                let mut encoded = Vec::new();
+                ciborium::ser::into_writer(document, &mut encoded)?;
+                let size: u32 = encoded
+                    .len()
+                    .try_into()
+                    .map_err(|_| anyhow!("Document is too large to encode"))?;
+                let mut frame = Vec::with_capacity(encoded.len() + 4);
+                frame.extend_from_slice(&size.to_be_bytes());
+                frame.extend(encoded);
+                Ok(frame)
+            },
+        }
+    }
+
+    /// Decode all documents stored in a value.
+    pub fn decode(&self, raw: &[u8]) -> Result<Vec<Value>> {
+        let mut values = Vec::new();
+        match self {
+            Format::Json => {
+                let text = str::from_utf8(raw)?;
+                for value in Deserializer::from_str(text).into_iter::<Value>() {
+                    values.push(value?);
+                }
+            },
+            Format::Framed => {
+                for frame in frames(raw)? {
+                    values.push(ciborium::de::from_reader(&frame[4..])?);
+                }
+            },
+        }
+        Ok(values)
+    }
+}
+
+/// Split a value in the framed format into each of the frames it contains. Each frame includes
+/// the length header.
+pub fn frames(raw: &[u8]) -> Result<Vec<&[u8]>> {
+    let mut found = Vec::new();
+    let mut rest = raw;
+    while !rest.is_empty() {
+        if rest.len() < 4 {
+            return Err(anyhow!("Truncated frame header in stored value"));
+        }
+        let size = u32::from_be_bytes(rest[..4].try_into()?) as usize;
+        if rest.len() < size + 4 {
+            return Err(anyhow!("Truncated frame in stored value"));
+        }
+        let (frame, remaining) = rest.split_at(size + 4);
+        found.push(frame);
+        rest = remaining;
+    }
+    Ok(found)
+}
+
+/// The compression RocksDB should use for the data in a column family.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    None,
+    Snappy,
+    Lz4,
+    Zlib,
+    Zstd,
+}
+
+impl Compression {
+    pub fn db_type(&self) -> DBCompressionType {
+        match self {
+            Compression::None => DBCompressionType::None,
+            Compression::Snappy => DBCompressionType::Snappy,
+            Compression::Lz4 => DBCompressionType::Lz4,
+            Compression::Zlib => DBCompressionType::Zlib,
+            Compression::Zstd => DBCompressionType::Zstd,
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        match raw {
+            "none" => Ok(Compression::None),
+            "snappy" => Ok(Compression::Snappy),
+            "lz4" => Ok(Compression::Lz4),
+            "zlib" => Ok(Compression::Zlib),
+            "zstd" => Ok(Compression::Zstd),
+            _ => Err(anyhow!("Unknown compression {}", raw)),
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    convert::TryInto,
+    fmt,
+    str,
+    str::FromStr,
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use serde_json::Value;
+
+use anyhow::{
+    anyhow,
+    Result,
+};
+
+use rnc_core::{
+    urs::Urs,
+    urs_taxid::UrsTaxid,
+};
+
+/// Describes how to extract the key of each document being indexed. This is a comma separated
+/// list of JSON paths, like `.id` or `.metadata.urs`, where each path is a '.' separated list
+/// of object fields or array indexes. The leading '.' is optional, so `urs,taxid` is also
+/// valid. If there are several paths the values found are joined with '_', so `urs,taxid`
+/// produces keys like `URS0000000001_9606`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Key {
+    paths: Vec<Vec<String>>,
+}
+
+impl Key {
+    /// Get the key of the given document. This fails if any path is missing from the document
+    /// or if it does not point to a string or number.
+    pub fn extract(&self, document: &Value) -> Result<String> {
+        let mut parts = Vec::with_capacity(self.paths.len());
+        for path in &self.paths {
+            let mut current = document;
+            for field in path {
+                let next = match current {
+                    Value::Object(map) => map.get(field),
+                    Value::Array(items) => field.parse::<usize>().ok().and_then(|i| items.get(i)),
+                    _ => None,
+                };
+                current = next.ok_or_else(|| anyhow!("Missing key field .{}", path.join(".")))?;
+            }
+
+            match current {
+                Value::String(value) => parts.push(value.to_string()),
+                Value::Number(value) => parts.push(value.to_string()),
+                _ => {
+                    return Err(anyhow!(
+                        "Key field .{} must be a string or number, found {}",
+                        path.join("."),
+                        current
+                    ))
+                },
+            }
+        }
+        Ok(parts.join("_"))
+    }
+
+    /// Parse the document and extract the key from it.
+    pub fn extract_from_str(&self, raw: &str) -> Result<String> {
+        let document: Value = serde_json::from_str(raw)?;
+        self.extract(&document)
+    }
+}
+
+impl Default for Key {
+    fn default() -> Self {
+        Self {
+            paths: vec![vec![String::from("id")]],
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        let mut paths = Vec::new();
+        for path in raw.split(',') {
+            let path = path.trim();
+            let path = path.strip_prefix('.').unwrap_or(path);
+            let fields: Vec<String> = path.split('.').map(String::from).collect();
+            if fields.iter().any(|f| f.is_empty()) {
+                return Err(anyhow!("Invalid key path '{}' in '{}'", path, raw));
+            }
+            paths.push(fields);
+        }
+        Ok(Self {
+            paths,
+        })
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let paths: Vec<String> = self.paths.iter().map(|p| format!(".{}", p.join("."))).collect();
+        write!(f, "{}", paths.join(","))
+    }
+}
+
+/// A check applied to each key as it is indexed. Keys which pass are rewritten to their
+/// canonical form, so small mistakes in an export, like a lowercase URS or trailing whitespace,
+/// do not produce separate keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyValidation {
+    /// Keys must be a URS like `URS0000614226`.
+    Urs,
+    /// Keys must be a URS and taxid like `URS0000614226_291828`.
+    UrsTaxid,
+}
+
+impl KeyValidation {
+    /// Check the key, producing its canonical form.
+    pub fn normalize(&self, key: &str) -> Result<String> {
+        match self {
+            KeyValidation::Urs => {
+                let urs = Urs::parse_any_case(key.trim())
+                    .map_err(|err| anyhow!("Key '{}' is not a URS, {}", key, err))?;
+                Ok(urs.to_string())
+            },
+            KeyValidation::UrsTaxid => {
+                let urs_taxid = UrsTaxid::parse_any_case(key.trim())
+                    .map_err(|err| anyhow!("Key '{}' is not a URS_taxid, {}", key, err))?;
+                Ok(urs_taxid.to_string())
+            },
+        }
+    }
+}
+
+impl FromStr for KeyValidation {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        match raw {
+            "urs" => Ok(KeyValidation::Urs),
+            "urs-taxid" => Ok(KeyValidation::UrsTaxid),
+            _ => Err(anyhow!("Unknown key validation {}, must be urs or urs-taxid", raw)),
+        }
+    }
+}
+
+/// How keys are stored in an index. This is chosen when an index is created and recorded in its
+/// metadata, keys are always given and produced as text and converted when reading or writing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyCodec {
+    /// Keys are stored as the UTF-8 text of the key.
+    Text,
+    /// Keys like `URS0000614226_291828` are stored as the URS and taxid, each as an 8 byte big
+    /// endian integer. This is smaller than the text and sorts by URS and then numerically by
+    /// taxid, so all entries for one URS are next to each other.
+    UrsTaxid,
+}
+
+impl Default for KeyCodec {
+    fn default() -> Self {
+        KeyCodec::Text
+    }
+}
+
+impl KeyCodec {
+    /// Convert a key to the bytes stored in the index.
+    pub fn encode(&self, key: &str) -> Result<Vec<u8>> {
+        match self {
+            KeyCodec::Text => Ok(key.as_bytes().to_vec()),
+            KeyCodec::UrsTaxid => {
+                let urs_taxid: UrsTaxid = key
+                    .parse()
+                    .map_err(|err| anyhow!("Key {} is not a URS_taxid, {}", key, err))?;
+                let mut encoded = Vec::with_capacity(16);
+                encoded.extend_from_slice(&urs_taxid.urs().to_be_bytes());
+                encoded.extend_from_slice(&urs_taxid.taxid().to_be_bytes());
+                Ok(encoded)
+            },
+        }
+    }
+
+    /// Convert the start of a key to the bytes which all stored keys starting with it begin
+    /// with. With the `UrsTaxid` codec this may be a complete key or a URS on its own, which
+    /// matches all taxids of that URS.
+    pub fn encode_prefix(&self, prefix: &str) -> Result<Vec<u8>> {
+        match (self, prefix.parse::<Urs>()) {
+            (KeyCodec::UrsTaxid, Ok(urs)) => Ok(u64::from(urs).to_be_bytes().to_vec()),
+            _ => self.encode(prefix),
+        }
+    }
+
+    /// Convert the bytes stored in the index back to the key.
+    pub fn decode(&self, raw: &[u8]) -> Result<String> {
+        match self {
+            KeyCodec::Text => Ok(str::from_utf8(raw)?.to_string()),
+            KeyCodec::UrsTaxid => {
+                if raw.len() != 16 {
+                    return Err(anyhow!("Stored key has {} bytes instead of 16", raw.len()));
+                }
+                let urs = u64::from_be_bytes(raw[..8].try_into()?);
+                let taxid = u64::from_be_bytes(raw[8..].try_into()?);
+                Ok(UrsTaxid::new(urs, taxid).to_string())
+            },
+        }
+    }
+}
+
+impl FromStr for KeyCodec {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        match raw {
+            "text" => Ok(KeyCodec::Text),
+            "urs-taxid" => Ok(KeyCodec::UrsTaxid),
+            _ => Err(anyhow!("Unknown key codec {}, must be text or urs-taxid", raw)),
+        }
+    }
+}
@@ -0,0 +1,9 @@
+pub mod encoding;
+pub mod key;
+pub mod merge;
+pub mod metadata;
+pub mod output;
+pub mod selection;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod store;
@@ -2,15 +2,103 @@ use std::path::PathBuf;
 extern crate log;
 use structopt::StructOpt;
 
-pub mod store;
+use kv::{
+    encoding::Compression,
+    key::{
+        Key,
+        KeyCodec,
+        KeyValidation,
+    },
+    merge::MergePolicy,
+    output::OutputFormat,
+    selection::{
+        Projection,
+        Selection,
+    },
+    store,
+};
+
+// Options shared by the commands which write to an index, this is not a doc comment as
+// structopt would use it as the about text of each command.
+#[derive(Debug, StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+struct IndexOptions {
+    /// Number of entries to write to the database at once.
+    #[structopt(short, long, default_value = "1000000")]
+    commit_size: usize,
+
+    /// Write sorted SST files of commit-size entries and ingest them, instead of writing
+    /// through the memtable. This is much faster when loading very large files.
+    #[structopt(long)]
+    bulk: bool,
+
+    /// How to find the key of each document, a comma separated list of JSON paths like
+    /// '.urs' or '.urs,.taxid'. If several are given the values are joined with '_'.
+    #[structopt(short, long, default_value = ".id")]
+    key: Key,
+
+    /// How keys are stored, either text or urs-taxid. With urs-taxid keys like
+    /// URS0000614226_291828 are stored as two integers, which is smaller and sorts taxids
+    /// numerically. This can only be set when the index is created.
+    #[structopt(long)]
+    key_codec: Option<KeyCodec>,
+
+    /// Check that each key is a urs or a urs-taxid, rewriting it to the canonical form.
+    /// Lines with keys which fail are quarantined, if a quarantine file is given.
+    #[structopt(long)]
+    validate_keys: Option<KeyValidation>,
+
+    /// Compression to use for the column families being written, one of none, snappy,
+    /// lz4, zlib or zstd. This is remembered for later writes to the same families.
+    #[structopt(long)]
+    compression: Option<Compression>,
+
+    /// How new values are combined with the existing values of the same key, one of
+    /// append, replace or union. This is remembered for later writes to the same families.
+    #[structopt(short, long)]
+    merge: Option<MergePolicy>,
+
+    /// Write lines which cannot be parsed, or have no key, to this file instead of failing.
+    #[structopt(short, long, parse(from_os_str))]
+    quarantine: Option<PathBuf>,
+}
+
+impl IndexOptions {
+    fn configure<'a>(&'a self, spec: &mut store::Spec<'a>) {
+        spec.set_commit_size(self.commit_size);
+        spec.set_bulk(self.bulk);
+        spec.set_key(self.key.clone());
+        spec.set_key_codec(self.key_codec);
+        spec.set_key_validation(self.validate_keys);
+        spec.set_compression(self.compression);
+        spec.set_merge_policy(self.merge);
+        spec.set_quarantine(self.quarantine.as_deref());
+    }
+}
+
+// Options shared by the commands which only read an index.
+#[derive(Debug, StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+struct ReadOptions {
+    /// Read the index as a secondary instance, keeping its own logs in this directory. This
+    /// allows reading an index which is still being written by another process.
+    #[structopt(long, parse(from_os_str))]
+    secondary: Option<PathBuf>,
+}
+
+impl ReadOptions {
+    fn configure<'a>(&'a self, spec: &mut store::Spec<'a>) {
+        spec.set_secondary(self.secondary.as_deref());
+    }
+}
 
 #[derive(Debug, StructOpt)]
 #[structopt(rename_all = "kebab-case")]
 enum Subcommand {
     /// Index a JSON document.
     Index {
-        #[structopt(short, long, default_value = "1000000")]
-        commit_size: usize,
+        #[structopt(flatten)]
+        options: IndexOptions,
 
         /// Type of data being indexed, eg, secondary_structure, hits, etc
         data_type: String,
@@ -24,9 +112,17 @@ enum Subcommand {
         output: PathBuf,
     },
 
+    /// Index several JSON files, each into a column family named after the file.
     IndexFiles {
+        #[structopt(flatten)]
+        options: IndexOptions,
+
+        /// Number of threads to use when reading files.
+        #[structopt(short, long, default_value = "4")]
+        threads: usize,
+
         #[structopt(parse(from_os_str))]
-        /// Filename of the raw json file, '-' means stdin.
+        /// Filename of a file listing the files to index, one per line, '-' means stdin.
         filename: PathBuf,
 
         /// Filename to store the index in.
@@ -41,6 +137,42 @@ enum Subcommand {
         #[structopt(short, long)]
         allow_missing: bool,
 
+        /// Number of threads to use when looking up keys.
+        #[structopt(short, long, default_value = "4")]
+        threads: usize,
+
+        /// Number of keys each thread fetches from the database at once.
+        #[structopt(short, long, default_value = "1000")]
+        batch_size: usize,
+
+        /// Comma separated list of the only column families to write.
+        #[structopt(long, require_delimiter = true)]
+        only: Vec<String>,
+
+        /// Comma separated list of column families to not write.
+        #[structopt(long, require_delimiter = true)]
+        exclude: Vec<String>,
+
+        /// Treat each line as a key prefix and write the data of every key starting with it. With
+        /// urs-taxid keys a URS finds the data for each of its taxids.
+        #[structopt(long)]
+        prefix: bool,
+
+        /// Fields to keep from the values of a column family, like 'rfam_hits=rfam_ids,score'.
+        /// This may be given once per column family, families without it keep all fields.
+        #[structopt(long, number_of_values = 1)]
+        fields: Vec<Projection>,
+
+        /// Format to write the data in, one of jsonl, csv, tsv or arrow. CSV and TSV have a
+        /// column per field of each column family, so the values must be flat objects, and a
+        /// line per combination of the values of each family. Arrow writes an IPC file with a
+        /// list of structs column per column family.
+        #[structopt(long, default_value = "jsonl")]
+        format: OutputFormat,
+
+        #[structopt(flatten)]
+        read: ReadOptions,
+
         /// Filename of the database file
         #[structopt(parse(from_os_str))]
         cache: PathBuf,
@@ -53,6 +185,170 @@ enum Subcommand {
         #[structopt(parse(from_os_str))]
         output: PathBuf,
     },
+
+    /// Write the data for all keys in the index, in key order, in the same format as lookup.
+    Dump {
+        /// Only dump keys which start with this prefix. With urs-taxid keys this must be a
+        /// complete key or a URS.
+        #[structopt(short, long)]
+        prefix: Option<String>,
+
+        /// Only dump keys which are greater than or equal to this key.
+        #[structopt(short, long)]
+        start: Option<String>,
+
+        /// Only dump keys which are less than this key.
+        #[structopt(short, long)]
+        end: Option<String>,
+
+        /// Comma separated list of column families to select keys from, defaults to all of
+        /// them. All data for each selected key is written, not only these families.
+        #[structopt(short, long, require_delimiter = true)]
+        families: Vec<String>,
+
+        /// How to select keys from the families, 'inner' requires the key to be in all of them
+        /// and 'outer' requires it to be in at least one.
+        #[structopt(short, long, default_value = "outer")]
+        join: store::Join,
+
+        #[structopt(flatten)]
+        read: ReadOptions,
+
+        /// Filename of the database file
+        #[structopt(parse(from_os_str))]
+        cache: PathBuf,
+
+        /// Filename to write the data to, '-' means stdout.
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+    },
+
+    /// Delete all data for each key in a file, then compact the index to reclaim the space.
+    Delete {
+        /// Comma separated list of column families to delete the keys from, defaults to all of
+        /// them.
+        #[structopt(short, long, require_delimiter = true)]
+        families: Vec<String>,
+
+        /// Number of keys to delete at once.
+        #[structopt(short, long, default_value = "100000")]
+        commit_size: usize,
+
+        /// Filename of the database file
+        #[structopt(parse(from_os_str))]
+        cache: PathBuf,
+
+        /// Filename of the keys to delete, '-' means stdin.
+        #[structopt(parse(from_os_str))]
+        filename: PathBuf,
+    },
+
+    /// Remove column families, and all data in them, from the index.
+    DropCf {
+        /// Filename of the database file
+        #[structopt(parse(from_os_str))]
+        cache: PathBuf,
+
+        /// Names of the column families to remove.
+        #[structopt(required = true)]
+        families: Vec<String>,
+    },
+
+    /// Create a checkpoint of the index, a complete copy which shares unchanged files with the
+    /// original. The release and creation time are recorded in the copy.
+    Checkpoint {
+        /// Name of the release the checkpoint is for.
+        #[structopt(short, long)]
+        release: Option<String>,
+
+        /// Filename of the database file
+        #[structopt(parse(from_os_str))]
+        cache: PathBuf,
+
+        /// Directory to create the checkpoint in, this must not exist.
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+    },
+
+    /// Compact column families of the index, optionally changing their compression. The
+    /// release and time of the compaction are recorded in the index.
+    Compact {
+        /// Compression to rewrite the column families with, one of none, snappy, lz4, zlib or
+        /// zstd. This is remembered for later writes to the same families.
+        #[structopt(long)]
+        compression: Option<Compression>,
+
+        /// Name of the release the index is for.
+        #[structopt(short, long)]
+        release: Option<String>,
+
+        /// Filename of the database file
+        #[structopt(parse(from_os_str))]
+        cache: PathBuf,
+
+        /// Names of the column families to compact, defaults to all of them.
+        families: Vec<String>,
+    },
+
+    /// Serve the index read-only over HTTP. This answers 'GET /families', 'GET /key/<id>' and
+    /// 'POST /lookup' with a JSON list of keys, all with JSON.
+    #[cfg(feature = "server")]
+    Serve {
+        /// Address to listen on, the default only accepts connections from this machine.
+        #[structopt(long, default_value = "127.0.0.1")]
+        host: String,
+
+        /// Port to listen on.
+        #[structopt(short, long, default_value = "8080")]
+        port: u16,
+
+        #[structopt(flatten)]
+        read: ReadOptions,
+
+        /// Filename of the database file
+        #[structopt(parse(from_os_str))]
+        cache: PathBuf,
+    },
+
+    /// Write a JSON summary of each column family in the index, with estimated key counts,
+    /// sizes and a sample of keys.
+    Stats {
+        /// Number of keys from each column family to include.
+        #[structopt(short, long, default_value = "10")]
+        sample: usize,
+
+        #[structopt(flatten)]
+        read: ReadOptions,
+
+        /// Filename of the database file
+        #[structopt(parse(from_os_str))]
+        cache: PathBuf,
+
+        /// Filename to write the summary to, '-' means stdout.
+        #[structopt(parse(from_os_str), default_value = "-")]
+        output: PathBuf,
+    },
+
+    /// Write all data stored for a single key, this is useful for debugging.
+    Get {
+        /// Only show the values in this column family.
+        #[structopt(short, long)]
+        family: Option<String>,
+
+        #[structopt(flatten)]
+        read: ReadOptions,
+
+        /// Filename of the database file
+        #[structopt(parse(from_os_str))]
+        cache: PathBuf,
+
+        /// The key to show.
+        key: String,
+
+        /// Filename to write the data to, '-' means stdout.
+        #[structopt(parse(from_os_str), default_value = "-")]
+        output: PathBuf,
+    },
 }
 
 #[derive(Debug, StructOpt)]
@@ -84,32 +380,136 @@ fn main() -> anyhow::Result<()> {
 
     match opt.command {
         Subcommand::Index {
-            commit_size,
+            options,
             data_type,
             filename,
             output,
         } => {
             let mut spec = store::Spec::new(&output);
-            spec.set_commit_size(commit_size);
+            options.configure(&mut spec);
             store::index(&spec, &data_type, &filename)?
         },
         Subcommand::IndexFiles {
+            options,
+            threads,
             filename,
             output,
         } => {
-            let spec = store::Spec::new(&output);
+            let mut spec = store::Spec::new(&output);
+            options.configure(&mut spec);
+            spec.set_threads(threads);
             store::index_files(&spec, &filename)?
         },
         Subcommand::Lookup {
             allow_missing,
+            threads,
+            batch_size,
+            only,
+            exclude,
+            prefix,
+            fields,
+            format,
+            read,
             cache,
             filename,
             output,
         } => {
             let mut spec = store::Spec::new(&cache);
+            read.configure(&mut spec);
             spec.set_allow_missing(allow_missing);
+            spec.set_threads(threads);
+            spec.set_batch_size(batch_size);
+            spec.set_selection(Selection::new(only, exclude, fields));
+            spec.set_prefix(prefix);
+            spec.set_output_format(format);
             store::lookup(&spec, &filename, &output)?
         },
+        Subcommand::Dump {
+            prefix,
+            start,
+            end,
+            families,
+            join,
+            read,
+            cache,
+            output,
+        } => {
+            let mut spec = store::Spec::new(&cache);
+            read.configure(&mut spec);
+            let range = store::KeyRange {
+                prefix,
+                start,
+                end,
+            };
+            store::dump(&spec, &range, &families, join, &output)?
+        },
+        Subcommand::Delete {
+            families,
+            commit_size,
+            cache,
+            filename,
+        } => {
+            let mut spec = store::Spec::new(&cache);
+            spec.set_commit_size(commit_size);
+            store::delete(&spec, &families, &filename)?
+        },
+        Subcommand::DropCf {
+            cache,
+            families,
+        } => {
+            let spec = store::Spec::new(&cache);
+            store::drop_families(&spec, &families)?
+        },
+        Subcommand::Checkpoint {
+            release,
+            cache,
+            output,
+        } => {
+            let spec = store::Spec::new(&cache);
+            store::checkpoint(&spec, &output, release.as_deref())?
+        },
+        Subcommand::Compact {
+            compression,
+            release,
+            cache,
+            families,
+        } => {
+            let mut spec = store::Spec::new(&cache);
+            spec.set_compression(compression);
+            store::compact(&spec, &families, release.as_deref())?
+        },
+        #[cfg(feature = "server")]
+        Subcommand::Serve {
+            host,
+            port,
+            read,
+            cache,
+        } => {
+            let mut spec = store::Spec::new(&cache);
+            read.configure(&mut spec);
+            store::serve(&spec, &host, port)?
+        },
+        Subcommand::Stats {
+            sample,
+            read,
+            cache,
+            output,
+        } => {
+            let mut spec = store::Spec::new(&cache);
+            read.configure(&mut spec);
+            store::stats(&spec, sample, &output)?
+        },
+        Subcommand::Get {
+            family,
+            read,
+            cache,
+            key,
+            output,
+        } => {
+            let mut spec = store::Spec::new(&cache);
+            read.configure(&mut spec);
+            store::get(&spec, &key, family.as_deref(), &output)?
+        },
     };
 
     Ok(())
@@ -0,0 +1,134 @@
+use std::{
+    collections::{
+        hash_map::DefaultHasher,
+        HashSet,
+    },
+    hash::{
+        Hash,
+        Hasher,
+    },
+    str::FromStr,
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use anyhow::{
+    anyhow,
+    Result,
+};
+
+use rocksdb::{
+    MergeOperands,
+    Options,
+};
+
+use crate::encoding::frames;
+
+/// How new values for a key are combined with the existing values in a column family.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MergePolicy {
+    /// Keep every value, so indexing the same document twice stores it twice.
+    Append,
+    /// Keep only the most recently indexed value.
+    Replace,
+    /// Keep each distinct value once, in the order first indexed, comparing values by a hash
+    /// of their content.
+    Union,
+}
+
+impl Default for MergePolicy {
+    fn default() -> Self {
+        MergePolicy::Append
+    }
+}
+
+impl MergePolicy {
+    /// If this policy has to split values into separate documents, which is only possible with
+    /// the framed format.
+    pub fn requires_frames(&self) -> bool {
+        *self != MergePolicy::Append
+    }
+
+    /// Set the merge operator for this policy in the options of a column family.
+    pub fn set_operator(&self, opts: &mut Options) {
+        match self {
+            MergePolicy::Append => {
+                opts.set_merge_operator_associative("append operator", concat_merge)
+            },
+            MergePolicy::Replace => {
+                opts.set_merge_operator_associative("replace operator", replace_merge)
+            },
+            MergePolicy::Union => {
+                opts.set_merge_operator_associative("union operator", union_merge)
+            },
+        }
+    }
+}
+
+impl FromStr for MergePolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        match raw {
+            "append" => Ok(MergePolicy::Append),
+            "replace" => Ok(MergePolicy::Replace),
+            "union" => Ok(MergePolicy::Union),
+            _ => Err(anyhow!("Unknown merge policy {}, must be append, replace or union", raw)),
+        }
+    }
+}
+
+fn concat_merge(
+    _new_key: &[u8],
+    existing_val: Option<&[u8]>,
+    operands: &MergeOperands,
+) -> Option<Vec<u8>> {
+    let mut result: Vec<u8> = Vec::with_capacity(operands.len());
+    existing_val.map(|v| {
+        for e in v {
+            result.push(*e)
+        }
+    });
+    for op in operands {
+        for e in op {
+            result.push(*e)
+        }
+    }
+    Some(result)
+}
+
+fn replace_merge(
+    _new_key: &[u8],
+    existing_val: Option<&[u8]>,
+    operands: &MergeOperands,
+) -> Option<Vec<u8>> {
+    let mut last = existing_val;
+    for op in operands {
+        last = Some(op);
+    }
+    let frames = frames(last.unwrap_or_default()).ok()?;
+    Some(frames.last().map(|f| f.to_vec()).unwrap_or_default())
+}
+
+fn union_merge(
+    _new_key: &[u8],
+    existing_val: Option<&[u8]>,
+    operands: &MergeOperands,
+) -> Option<Vec<u8>> {
+    let mut seen = HashSet::new();
+    let mut result = Vec::new();
+    for value in existing_val.into_iter().chain(operands) {
+        for frame in frames(value).ok()? {
+            let mut hasher = DefaultHasher::new();
+            frame.hash(&mut hasher);
+            if seen.insert(hasher.finish()) {
+                result.extend_from_slice(frame);
+            }
+        }
+    }
+    Some(result)
+}
@@ -0,0 +1,124 @@
+use std::{
+    collections::BTreeMap,
+    time::{
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use anyhow::{
+    anyhow,
+    Result,
+};
+
+use rocksdb::DB;
+
+use crate::{
+    encoding::{
+        Compression,
+        Format,
+    },
+    key::KeyCodec,
+    merge::MergePolicy,
+};
+
+/// The column family which the metadata is stored in, this never contains indexed data.
+pub const METADATA_FAMILY: &str = "metadata";
+
+/// The key, in the metadata column family, which the metadata is stored under.
+const METADATA_KEY: &[u8] = b"metadata";
+
+/// Information about how an index was written. This is stored as JSON in its own column
+/// family, see `METADATA_FAMILY`. Indexes written before the metadata existed have none, and
+/// are treated as using the original JSON text format.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Metadata {
+    /// The version of the value layout, see `Format::from_version`.
+    pub format_version: u32,
+
+    /// The compression to use for each column family, families which are not listed use the
+    /// RocksDB default.
+    pub compression: BTreeMap<String, Compression>,
+
+    /// The merge policy of each column family, families which are not listed append values.
+    pub merge: BTreeMap<String, MergePolicy>,
+
+    /// How keys are stored, this is the same for all column families.
+    pub key_codec: KeyCodec,
+
+    /// The name of the release the index was built for, if one has been recorded.
+    pub release: Option<String>,
+
+    /// When this copy of the index was created by a checkpoint or compaction, in seconds since
+    /// the Unix epoch.
+    pub created: Option<u64>,
+}
+
+impl Default for Metadata {
+    fn default() -> Self {
+        Self {
+            format_version: Format::Json.version(),
+            compression: BTreeMap::new(),
+            merge: BTreeMap::new(),
+            key_codec: KeyCodec::default(),
+            release: None,
+            created: None,
+        }
+    }
+}
+
+impl Metadata {
+    /// The metadata to use when creating a new index.
+    pub fn current() -> Self {
+        Self {
+            format_version: Format::Framed.version(),
+            ..Self::default()
+        }
+    }
+
+    /// Read the metadata of the database, if there is any. This is None if the database was
+    /// opened without the metadata column family.
+    pub fn read(db: &DB) -> Result<Option<Self>> {
+        let family = match db.cf_handle(METADATA_FAMILY) {
+            Some(family) => family,
+            None => return Ok(None),
+        };
+        match db.get_cf(family, METADATA_KEY)? {
+            None => Ok(None),
+            Some(raw) => Ok(Some(serde_json::from_slice(&raw)?)),
+        }
+    }
+
+    /// Write the metadata, the database must have been opened with the metadata column family.
+    pub fn write(&self, db: &DB) -> Result<()> {
+        let family = db
+            .cf_handle(METADATA_FAMILY)
+            .ok_or_else(|| anyhow!("Index has no {} column family", METADATA_FAMILY))?;
+        db.put_cf(family, METADATA_KEY, serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+
+    pub fn format(&self) -> Result<Format> {
+        Format::from_version(self.format_version)
+    }
+
+    /// Record that the index was created now, for the given release if there is one. Without a
+    /// release any previously recorded one is kept.
+    pub fn record_release(&mut self, release: Option<&str>) -> Result<()> {
+        if let Some(release) = release {
+            self.release = Some(release.to_string());
+        }
+        self.created = Some(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs());
+        Ok(())
+    }
+
+    pub fn merge_policy(&self, family: &str) -> MergePolicy {
+        self.merge.get(family).copied().unwrap_or_default()
+    }
+}
@@ -0,0 +1,401 @@
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    io::{
+        BufReader,
+        BufWriter,
+        Seek,
+        SeekFrom,
+        Write,
+    },
+    str::FromStr,
+};
+
+#[cfg(feature = "arrow")]
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+};
+
+use serde_json::{
+    Deserializer,
+    Map,
+    Value,
+};
+
+use anyhow::{
+    anyhow,
+    Result,
+};
+
+#[cfg(feature = "arrow")]
+use arrow::{
+    error::ArrowError,
+    ipc::writer::FileWriter,
+    json::reader::{
+        infer_json_schema_from_iterator,
+        ReaderBuilder,
+    },
+};
+
+use crate::selection::Selection;
+
+/// The grouped data of a single key, as produced by `Store::grouped`.
+pub type Row = Map<String, Value>;
+
+/// The number of rows in each Arrow record batch.
+#[cfg(feature = "arrow")]
+const ARROW_BATCH_SIZE: usize = 1024;
+
+/// The formats lookup can write the data for each key in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// One JSON object per line, this keeps all data exactly as it was indexed.
+    #[default]
+    Jsonl,
+    /// Comma separated values with one column per field of each column family.
+    Csv,
+    /// Like `Csv` but tab separated.
+    Tsv,
+    /// An Arrow IPC file with one list of structs column per column family.
+    #[cfg(feature = "arrow")]
+    Arrow,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        match raw {
+            "jsonl" => Ok(OutputFormat::Jsonl),
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            #[cfg(feature = "arrow")]
+            "arrow" => Ok(OutputFormat::Arrow),
+            #[cfg(not(feature = "arrow"))]
+            "arrow" => Err(anyhow!("Arrow output needs kv to be built with the arrow feature")),
+            _ => Err(anyhow!("Unknown output format {}, must be jsonl, csv, tsv or arrow", raw)),
+        }
+    }
+}
+
+impl OutputFormat {
+    /// Create a writer for rows with data from the given column families, in this format.
+    pub fn writer<'a>(
+        &self,
+        out: Box<dyn Write + 'a>,
+        families: Vec<String>,
+        selection: &Selection,
+    ) -> Result<Box<dyn RowWriter + 'a>> {
+        Ok(match self {
+            OutputFormat::Jsonl => Box::new(JsonlWriter {
+                out,
+            }),
+            OutputFormat::Csv => Box::new(DelimitedWriter::new(out, b',', families, selection)?),
+            OutputFormat::Tsv => Box::new(DelimitedWriter::new(out, b'\t', families, selection)?),
+            #[cfg(feature = "arrow")]
+            OutputFormat::Arrow => Box::new(ArrowWriter {
+                out,
+                spill: Spill::new()?,
+                shapes: BTreeMap::new(),
+            }),
+        })
+    }
+}
+
+/// Something which writes the rows produced by a lookup. Rows are given in blocks, formats
+/// which need to know the columns up front keep the rows until they have seen all of them.
+pub trait RowWriter {
+    /// Write the grouped data of several keys.
+    fn write_rows(&mut self, rows: &[Row]) -> Result<()>;
+
+    /// Write anything which is still buffered, along with any footer the format needs.
+    fn finish(self: Box<Self>) -> Result<()>;
+}
+
+struct JsonlWriter<'a> {
+    out: Box<dyn Write + 'a>,
+}
+
+impl<'a> RowWriter for JsonlWriter<'a> {
+    fn write_rows(&mut self, rows: &[Row]) -> Result<()> {
+        for row in rows {
+            serde_json::to_writer(&mut self.out, row)?;
+            writeln!(self.out)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// Rows kept in a temporary file, for formats which must see every row before writing any.
+struct Spill {
+    file: BufWriter<File>,
+}
+
+impl Spill {
+    fn new() -> Result<Self> {
+        Ok(Self {
+            file: BufWriter::new(tempfile::tempfile()?),
+        })
+    }
+
+    fn push(&mut self, row: &Row) -> Result<()> {
+        serde_json::to_writer(&mut self.file, row)?;
+        writeln!(self.file)?;
+        Ok(())
+    }
+
+    /// Read all rows pushed so far, in the order they were pushed. This can be called several
+    /// times, but no rows may be pushed while reading.
+    fn rows(&mut self) -> Result<impl Iterator<Item = Result<Row>>> {
+        self.file.flush()?;
+        let mut file = self.file.get_ref().try_clone()?;
+        file.seek(SeekFrom::Start(0))?;
+        let rows = Deserializer::from_reader(BufReader::new(file)).into_iter::<Row>();
+        Ok(rows.map(|row| Ok(row?)))
+    }
+}
+
+/// Writes CSV or TSV, with an `id` column and then a `family.field` column for each field of
+/// each family. The fields are those given in the selection or, for families without any, all
+/// fields found in any row. In that case rows are kept until all have been seen, as the header
+/// depends on them.
+///
+/// A key with several values in a family is written as one line per value, repeating the id and
+/// the other families. When this happens in several families the lines are the cartesian
+/// product of their values, so a key with n values in one family and m in another is written
+/// as n × m lines. Use jsonl or arrow to keep one row per key.
+struct DelimitedWriter<'a> {
+    out: csv::Writer<Box<dyn Write + 'a>>,
+    families: Vec<String>,
+    selected: Vec<Option<Vec<String>>>,
+    found: Vec<BTreeSet<String>>,
+    spill: Option<Spill>,
+    columns: Option<Vec<(String, Vec<String>)>>,
+}
+
+impl<'a> DelimitedWriter<'a> {
+    fn new(
+        out: Box<dyn Write + 'a>,
+        delimiter: u8,
+        families: Vec<String>,
+        selection: &Selection,
+    ) -> Result<Self> {
+        let selected: Vec<Option<Vec<String>>> =
+            families.iter().map(|f| selection.fields(f).map(|f| f.to_vec())).collect();
+        let spill = match selected.iter().all(Option::is_some) {
+            true => None,
+            false => Some(Spill::new()?),
+        };
+        Ok(Self {
+            out: csv::WriterBuilder::new().delimiter(delimiter).from_writer(out),
+            found: vec![BTreeSet::new(); families.len()],
+            families,
+            selected,
+            spill,
+            columns: None,
+        })
+    }
+
+    /// Work out the fields of each family, from the selection or the fields found in the rows,
+    /// and write the header.
+    fn write_header(&mut self) -> Result<()> {
+        let mut columns = Vec::with_capacity(self.families.len());
+        for ((family, selected), found) in self.families.iter().zip(&self.selected).zip(&self.found)
+        {
+            let fields = match selected {
+                Some(fields) => fields.clone(),
+                None => found.iter().cloned().collect(),
+            };
+            columns.push((family.to_string(), fields));
+        }
+
+        let mut header = vec![String::from("id")];
+        for (family, fields) in &columns {
+            header.extend(fields.iter().map(|field| format!("{}.{}", family, field)));
+        }
+        self.out.write_record(&header)?;
+        self.columns = Some(columns);
+        Ok(())
+    }
+
+    fn write_row(&mut self, row: &Row) -> Result<()> {
+        let columns = self.columns.as_ref().ok_or_else(|| anyhow!("Header was not written"))?;
+        let id = cell("id", row.get("id"))?;
+        let mut records = vec![vec![id]];
+        for (family, fields) in columns {
+            let mut cells = Vec::new();
+            for value in values(row, family) {
+                let value = object(family, value)?;
+                if let Some(field) = value.keys().find(|field| !fields.contains(field)) {
+                    return Err(anyhow!("Field {}.{} is not one of the columns", family, field));
+                }
+                let name = |field| format!("{}.{}", family, field);
+                cells.push(
+                    fields
+                        .iter()
+                        .map(|field| cell(&name(field), value.get(field)))
+                        .collect::<Result<Vec<String>>>()?,
+                );
+            }
+            if cells.is_empty() {
+                cells.push(vec![String::new(); fields.len()]);
+            }
+            records = records
+                .into_iter()
+                .flat_map(|record| {
+                    cells.iter().map(move |cells| {
+                        let mut record = record.clone();
+                        record.extend(cells.iter().cloned());
+                        record
+                    })
+                })
+                .collect();
+        }
+        for record in records {
+            self.out.write_record(&record)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> RowWriter for DelimitedWriter<'a> {
+    fn write_rows(&mut self, rows: &[Row]) -> Result<()> {
+        if let Some(spill) = self.spill.as_mut() {
+            for row in rows {
+                for ((family, selected), found) in
+                    self.families.iter().zip(&self.selected).zip(self.found.iter_mut())
+                {
+                    if selected.is_none() {
+                        for value in values(row, family) {
+                            found.extend(object(family, value)?.keys().cloned());
+                        }
+                    }
+                }
+                spill.push(row)?;
+            }
+            return Ok(());
+        }
+
+        if self.columns.is_none() {
+            self.write_header()?;
+        }
+        rows.iter().try_for_each(|row| self.write_row(row))
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        if self.columns.is_none() {
+            self.write_header()?;
+        }
+        if let Some(mut spill) = self.spill.take() {
+            for row in spill.rows()? {
+                self.write_row(&row?)?;
+            }
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// The values of a family in a row.
+fn values<'r>(row: &'r Row, family: &str) -> &'r [Value] {
+    match row.get(family) {
+        Some(Value::Array(values)) => values,
+        _ => &[],
+    }
+}
+
+fn object<'v>(family: &str, value: &'v Value) -> Result<&'v Map<String, Value>> {
+    value.as_object().ok_or_else(|| {
+        anyhow!("Values of {} must be objects to write them as CSV or TSV, found {}", family, value)
+    })
+}
+
+fn cell(name: &str, value: Option<&Value>) -> Result<String> {
+    match value {
+        None | Some(Value::Null) => Ok(String::new()),
+        Some(Value::String(value)) => Ok(value.to_string()),
+        Some(Value::Bool(value)) => Ok(value.to_string()),
+        Some(Value::Number(value)) => Ok(value.to_string()),
+        Some(value) => Err(anyhow!(
+            "Field {} must be a string, number or boolean to write it as CSV or TSV, found {}",
+            name,
+            value
+        )),
+    }
+}
+
+/// The shape of a JSON value, which is all that Arrow uses when inferring a schema. Scalars are
+/// replaced by a placeholder of the same type and repeated shapes in arrays are removed, so rows
+/// with the same fields and types have the same shape.
+#[cfg(feature = "arrow")]
+fn shape(value: &Value) -> Value {
+    match value {
+        Value::Null => Value::Null,
+        Value::Bool(_) => Value::Bool(false),
+        Value::Number(number) if number.is_i64() => Value::from(0),
+        Value::Number(_) => Value::from(0.0),
+        Value::String(_) => Value::from(""),
+        Value::Array(values) => {
+            let mut shapes: Vec<Value> = Vec::new();
+            for value in values.iter().map(shape) {
+                if !shapes.contains(&value) {
+                    shapes.push(value);
+                }
+            }
+            Value::Array(shapes)
+        },
+        Value::Object(fields) => {
+            Value::Object(fields.iter().map(|(name, value)| (name.clone(), shape(value))).collect())
+        },
+    }
+}
+
+/// Writes an Arrow IPC file. The schema depends on all rows, so rows are kept until all have
+/// been seen and then decoded against it, failing on any field which does not fit. The schema is
+/// inferred from the distinct shapes of the rows, which are collected as the rows are kept.
+#[cfg(feature = "arrow")]
+struct ArrowWriter<'a> {
+    out: Box<dyn Write + 'a>,
+    spill: Spill,
+    shapes: BTreeMap<String, Value>,
+}
+
+#[cfg(feature = "arrow")]
+impl<'a> RowWriter for ArrowWriter<'a> {
+    fn write_rows(&mut self, rows: &[Row]) -> Result<()> {
+        for row in rows {
+            let shape = shape(&Value::Object(row.clone()));
+            self.shapes.entry(serde_json::to_string(&shape)?).or_insert(shape);
+            self.spill.push(row)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        let shapes = self.shapes.values().map(Ok::<_, ArrowError>);
+        let schema = Arc::new(infer_json_schema_from_iterator(shapes)?);
+        let mut writer = FileWriter::try_new(self.out, &schema)?;
+        let mut decoder = ReaderBuilder::new(schema)
+            .with_strict_mode(true)
+            .with_coerce_primitive(true)
+            .build_decoder()?;
+
+        let mut rows = self.spill.rows()?.peekable();
+        while rows.peek().is_some() {
+            let batch = rows.by_ref().take(ARROW_BATCH_SIZE).collect::<Result<Vec<Row>>>()?;
+            decoder.serialize(&batch)?;
+            if let Some(batch) = decoder.flush()? {
+                writer.write(&batch)?;
+            }
+        }
+        writer.finish()?;
+        writer.into_inner()?.flush()?;
+        Ok(())
+    }
+}
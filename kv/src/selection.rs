@@ -0,0 +1,107 @@
+use std::{
+    collections::HashMap,
+    str::FromStr,
+};
+
+use serde_json::{
+    Map,
+    Value,
+};
+
+use anyhow::{
+    anyhow,
+    Result,
+};
+
+/// The fields to keep from the values of a single column family, written as
+/// `family=field1,field2`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Projection {
+    pub family: String,
+    pub fields: Vec<String>,
+}
+
+impl FromStr for Projection {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        let mut parts = raw.splitn(2, '=');
+        let family = parts.next().unwrap_or_default().trim();
+        let fields: Vec<String> = parts
+            .next()
+            .ok_or_else(|| anyhow!("Projection '{}' must be like family=field1,field2", raw))?
+            .split(',')
+            .map(|field| field.trim().to_string())
+            .collect();
+        if family.is_empty() || fields.iter().any(|field| field.is_empty()) {
+            return Err(anyhow!("Projection '{}' must be like family=field1,field2", raw));
+        }
+        Ok(Self {
+            family: family.to_string(),
+            fields,
+        })
+    }
+}
+
+/// Which column families, and which fields of the values in each, to include when looking up
+/// keys. By default all families and all fields are included.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selection {
+    only: Vec<String>,
+    exclude: Vec<String>,
+    fields: HashMap<String, Vec<String>>,
+}
+
+impl Selection {
+    pub fn new(only: Vec<String>, exclude: Vec<String>, projections: Vec<Projection>) -> Self {
+        let mut fields: HashMap<String, Vec<String>> = HashMap::new();
+        for projection in projections {
+            fields.entry(projection.family).or_default().extend(projection.fields);
+        }
+        Self {
+            only,
+            exclude,
+            fields,
+        }
+    }
+
+    /// Select the families to use from all families in an index, keeping their order. This
+    /// fails if any family named in the selection is not in the index.
+    pub fn families(&self, all: &[String]) -> Result<Vec<String>> {
+        let named = self.only.iter().chain(self.exclude.iter()).chain(self.fields.keys());
+        for name in named {
+            if !all.contains(name) {
+                return Err(anyhow!("Unknown column family {}", name));
+            }
+        }
+
+        Ok(all
+            .iter()
+            .filter(|name| self.only.is_empty() || self.only.contains(name))
+            .filter(|name| !self.exclude.contains(name))
+            .cloned()
+            .collect())
+    }
+
+    /// The fields selected for the given family, if only some of them are selected.
+    pub fn fields(&self, family: &str) -> Option<&[String]> {
+        self.fields.get(family).map(|fields| fields.as_slice())
+    }
+
+    /// Remove all fields which are not selected from a value of the given family. Values which
+    /// are not objects are never changed.
+    pub fn project(&self, family: &str, value: Value) -> Value {
+        match (self.fields.get(family), value) {
+            (Some(fields), Value::Object(mut map)) => {
+                let mut projected = Map::new();
+                for field in fields {
+                    if let Some(found) = map.remove(field) {
+                        projected.insert(field.to_string(), found);
+                    }
+                }
+                Value::Object(projected)
+            },
+            (_, value) => value,
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    io::Cursor,
+    net::SocketAddr,
+};
+
+use serde_json::{
+    json,
+    Value,
+};
+
+use anyhow::{
+    anyhow,
+    Result,
+};
+
+use percent_encoding::percent_decode_str;
+
+use tiny_http::{
+    Header,
+    Method,
+    Request,
+    Response,
+};
+
+use crate::store::Store;
+
+/// A small read-only HTTP server for an index, meant for looking at an index interactively.
+/// All responses are JSON, and it answers:
+///
+/// * `GET /families` with the names of all column families.
+/// * `GET /key/<id>` with the grouped data of the key, as written by lookup. The key may be
+///   percent encoded.
+/// * `POST /lookup` with the grouped data of each key in the JSON list of keys in the body.
+///   Keys without data are null.
+///
+/// Keys which cannot be stored in the index, like a key which is not a URS_taxid in an index
+/// using the urs-taxid key codec, are answered with a 400.
+pub struct Server {
+    store: Store,
+    http: tiny_http::Server,
+}
+
+impl Server {
+    /// Start listening on the address, like `127.0.0.1:8080`. Requests are only answered once
+    /// `run` is called.
+    pub fn bind(store: Store, address: &str) -> Result<Self> {
+        let http = tiny_http::Server::http(address)
+            .map_err(|err| anyhow!("Could not listen on {}, {}", address, err))?;
+        Ok(Self {
+            store,
+            http,
+        })
+    }
+
+    /// The address the server is listening on, this is useful when binding to port 0.
+    pub fn address(&self) -> Result<SocketAddr> {
+        self.http.server_addr().to_ip().ok_or_else(|| anyhow!("Server is not listening on IP"))
+    }
+
+    /// Answer requests, one at a time, forever.
+    pub fn run(&self) -> Result<()> {
+        for mut request in self.http.incoming_requests() {
+            let (status, body) = match self.route(&mut request) {
+                Ok(answer) => answer,
+                Err(err) => (500, json!({ "error": err.to_string() })),
+            };
+            log::info!("{} {} {}", request.method(), request.url(), status);
+            let response = json_response(status, &body)?;
+            if let Err(err) = request.respond(response) {
+                log::warn!("Could not send response, {}", err);
+            }
+        }
+        Ok(())
+    }
+
+    fn route(&self, request: &mut Request) -> Result<(u16, Value)> {
+        let url = request.url().to_string();
+        let path = url.split('?').next().unwrap_or_default();
+        match (request.method(), path) {
+            (Method::Get, "/families") => Ok((200, json!(self.store.families()))),
+            (Method::Get, path) if path.starts_with("/key/") => {
+                let key = match percent_decode_str(&path["/key/".len()..]).decode_utf8() {
+                    Ok(key) => key,
+                    Err(err) => {
+                        let message = format!("Key must be percent encoded UTF-8, {}", err);
+                        return Ok((400, json!({ "error": message })));
+                    },
+                };
+                if let Err(err) = self.store.key_codec().encode(&key) {
+                    return Ok((400, json!({ "error": err.to_string() })));
+                }
+                match self.store.grouped(&key)? {
+                    Some(data) => Ok((200, Value::Object(data))),
+                    None => Ok((404, json!({ "error": format!("No data found for key {}", key) }))),
+                }
+            },
+            (Method::Post, "/lookup") => {
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body)?;
+                let keys: Vec<String> = match serde_json::from_str(&body) {
+                    Ok(keys) => keys,
+                    Err(err) => {
+                        let message = format!("Body must be a JSON list of keys, {}", err);
+                        return Ok((400, json!({ "error": message })));
+                    },
+                };
+                let codec = self.store.key_codec();
+                if let Some(err) = keys.iter().find_map(|key| codec.encode(key).err()) {
+                    return Ok((400, json!({ "error": err.to_string() })));
+                }
+                Ok((200, json!(self.store.grouped_batch(&keys)?)))
+            },
+            (_, "/families") | (_, "/lookup") => {
+                Ok((405, json!({ "error": format!("Method not allowed for {}", path) })))
+            },
+            (_, path) if path.starts_with("/key/") => {
+                Ok((405, json!({ "error": format!("Method not allowed for {}", path) })))
+            },
+            _ => Ok((404, json!({ "error": format!("Unknown path {}", path) }))),
+        }
+    }
+}
+
+fn json_response(status: u16, body: &Value) -> Result<Response<Cursor<Vec<u8>>>> {
+    let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+        .map_err(|_| anyhow!("Invalid content type header"))?;
+    Ok(Response::from_data(serde_json::to_vec(body)?)
+        .with_status_code(status)
+        .with_header(header))
+}
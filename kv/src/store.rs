@@ -1,54 +1,102 @@
 use std::{
-    collections::HashMap,
-    fs::File,
+    collections::{
+        BTreeMap,
+        HashMap,
+    },
     io::{
         BufRead,
-        BufReader,
+        Write,
     },
     path::{
         Path,
         PathBuf,
     },
-    str,
-    thread,
+    str::FromStr,
+    sync::atomic::{
+        AtomicBool,
+        Ordering,
+    },
 };
 
-use serde_query::{
-    DeserializeQuery,
-    Query,
-};
+use serde::Serialize;
 
 use serde_json::{
-    Deserializer,
+    Map,
     Value,
 };
 
-use anyhow::Result;
+use anyhow::{
+    anyhow,
+    Context,
+    Result,
+};
+
+use crate::{
+    encoding::{
+        Compression,
+        Format,
+    },
+    key::{
+        Key,
+        KeyCodec,
+        KeyValidation,
+    },
+    merge::MergePolicy,
+    metadata::{
+        Metadata,
+        METADATA_FAMILY,
+    },
+    output::{
+        OutputFormat,
+        Row,
+        RowWriter,
+    },
+    selection::Selection,
+};
+
+#[cfg(feature = "server")]
+use crate::server::Server;
 
 use crossbeam_channel::{
+    bounded,
     unbounded,
     Sender,
 };
 
+use tempfile::TempDir;
+
 use rocksdb::{
+    checkpoint::Checkpoint,
+    BottommostLevelCompaction,
     ColumnFamily,
     ColumnFamilyDescriptor,
-    MergeOperands,
+    CompactOptions,
+    DBRawIterator,
+    IngestExternalFileOptions,
+    IteratorMode,
     Options,
+    SstFileWriter,
+    WriteBatch,
     DB,
 };
 
-#[derive(DeserializeQuery)]
-struct DocId {
-    #[query(".id")]
-    id: String,
-}
-
 pub struct Spec<'a> {
     path: &'a Path,
     allow_missing: bool,
     commit_size: usize,
+    bulk: bool,
+    key: Key,
+    key_codec: Option<KeyCodec>,
+    validation: Option<KeyValidation>,
+    compression: Option<Compression>,
+    merge: Option<MergePolicy>,
+    quarantine: Option<&'a Path>,
+    secondary: Option<&'a Path>,
+    selection: Selection,
+    prefix: bool,
+    output_format: OutputFormat,
     threads: usize,
+    batch_size: usize,
 }
 
 impl<'a> Spec<'a> {
@@ -57,229 +105,1213 @@ impl<'a> Spec<'a> {
             path: path,
             allow_missing: false,
             commit_size: 1_000_000usize,
+            bulk: false,
+            key: Key::default(),
+            key_codec: None,
+            validation: None,
+            compression: None,
+            merge: None,
+            quarantine: None,
+            secondary: None,
+            selection: Selection::default(),
+            prefix: false,
+            output_format: OutputFormat::default(),
             threads: 4,
+            batch_size: 1_000usize,
         }
     }
 
+    pub fn set_threads(&mut self, threads: usize) -> () {
+        self.threads = threads.max(1);
+    }
+
+    pub fn set_batch_size(&mut self, batch_size: usize) -> () {
+        self.batch_size = batch_size.max(1);
+    }
+
     pub fn set_allow_missing(&mut self, allow_missing: bool) -> () {
         self.allow_missing = allow_missing;
     }
 
     pub fn set_commit_size(&mut self, commit_size: usize) -> () {
-        self.commit_size = commit_size;
+        self.commit_size = commit_size.max(1);
+    }
+
+    /// In bulk mode indexing writes each chunk of `commit_size` entries to a sorted SST file
+    /// which is ingested into the database directly, skipping the memtable entirely.
+    pub fn set_bulk(&mut self, bulk: bool) -> () {
+        self.bulk = bulk;
+    }
+
+    /// Set how the key of each indexed document is found, the default is the `.id` field.
+    pub fn set_key(&mut self, key: Key) -> () {
+        self.key = key;
+    }
+
+    /// Set how keys are stored when creating an index. Existing indexes always keep the codec
+    /// they were created with, asking for a different one is an error.
+    pub fn set_key_codec(&mut self, key_codec: Option<KeyCodec>) -> () {
+        self.key_codec = key_codec;
+    }
+
+    /// Check, and normalize, each key as it is indexed. Lines with keys which fail are treated
+    /// like any other invalid line, so they are quarantined if there is a quarantine file.
+    pub fn set_key_validation(&mut self, validation: Option<KeyValidation>) -> () {
+        self.validation = validation;
+    }
+
+    /// Set the compression used for the column families being indexed. This is remembered in
+    /// the index so later writes to the same families use it as well.
+    pub fn set_compression(&mut self, compression: Option<Compression>) -> () {
+        self.compression = compression;
+    }
+
+    /// Set how new values are merged with existing ones in the column families being indexed.
+    /// Like compression this is remembered in the index, families which have never had a policy
+    /// set append values.
+    pub fn set_merge_policy(&mut self, merge: Option<MergePolicy>) -> () {
+        self.merge = merge;
+    }
+
+    /// Write lines which cannot be indexed to this file, instead of failing on the first one.
+    pub fn set_quarantine(&mut self, quarantine: Option<&'a Path>) -> () {
+        self.quarantine = quarantine;
+    }
+
+    /// Read the index as a secondary instance, keeping its logs in the given directory. This
+    /// allows reading an index which is still being written, otherwise it is opened read-only.
+    pub fn set_secondary(&mut self, secondary: Option<&'a Path>) -> () {
+        self.secondary = secondary;
+    }
+
+    /// Set the column families and fields which lookups will write.
+    pub fn set_selection(&mut self, selection: Selection) -> () {
+        self.selection = selection;
     }
+
+    /// Treat each key given to lookup as a prefix, and write the data of every key which starts
+    /// with it.
+    pub fn set_prefix(&mut self, prefix: bool) -> () {
+        self.prefix = prefix;
+    }
+
+    /// Set the format lookup writes the data in, the default is JSONL.
+    pub fn set_output_format(&mut self, output_format: OutputFormat) -> () {
+        self.output_format = output_format;
+    }
+
+    fn access(&self) -> Access {
+        match self.secondary {
+            Some(path) => Access::Secondary(path.to_path_buf()),
+            None => Access::ReadOnly,
+        }
+    }
+}
+
+/// Encoded keys and values waiting to be written to an SST file.
+type Run = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// Writes the entries being indexed to the database in chunks of `commit_size` entries. Each
+/// chunk is either written as a single `WriteBatch` or, in bulk mode, sorted and written to an
+/// SST file which is then ingested. All entries are merged so they are appended to any existing
+/// data for the same key.
+struct Loader<'a> {
+    spec: &'a Spec<'a>,
+    db: &'a DB,
+    metadata: &'a Metadata,
+    /// Where SST files are written before being ingested, only created in bulk mode.
+    scratch: Option<TempDir>,
+    batch: WriteBatch,
+    runs: HashMap<String, Run>,
+    written: usize,
+    count: usize,
 }
 
-fn concat_merge(
-    _new_key: &[u8],
-    existing_val: Option<&[u8]>,
-    operands: &mut MergeOperands,
-) -> Option<Vec<u8>> {
-    let mut result: Vec<u8> = Vec::with_capacity(operands.size_hint().0);
-    existing_val.map(|v| {
-        for e in v {
-            result.push(*e)
+impl<'a> Loader<'a> {
+    fn new(spec: &'a Spec<'a>, db: &'a DB, metadata: &'a Metadata) -> Result<Self> {
+        // The SST files are kept next to, not inside, the database so that they can be moved
+        // into it instead of copied.
+        let scratch = if spec.bulk {
+            let parent = match spec.path.parent() {
+                Some(parent) if parent != Path::new("") => parent,
+                _ => Path::new("."),
+            };
+            let dir = tempfile::Builder::new()
+                .prefix(".kv-bulk-")
+                .tempdir_in(parent)
+                .with_context(|| format!("Could not create a directory in {}", parent.display()))?;
+            Some(dir)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            spec,
+            db,
+            metadata,
+            scratch,
+            batch: WriteBatch::default(),
+            runs: HashMap::new(),
+            written: 0,
+            count: 0,
+        })
+    }
+
+    fn family(&self, name: &str) -> Result<&'a ColumnFamily> {
+        self.db.cf_handle(name).ok_or_else(|| anyhow!("Unknown column family {}", name))
+    }
+
+    fn add(&mut self, family: &str, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
+        self.count += 1;
+        if !self.spec.bulk {
+            self.batch.merge_cf(self.family(family)?, key, value);
+            if self.batch.len() >= self.spec.commit_size {
+                self.commit()?;
+            }
+            return Ok(());
+        }
+
+        let run = self.runs.entry(family.to_string()).or_default();
+        run.push((key, value));
+        if run.len() >= self.spec.commit_size {
+            self.ingest(family)?;
         }
-    });
-    for op in operands {
-        for e in op {
-            result.push(*e)
+        Ok(())
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        let batch = std::mem::take(&mut self.batch);
+        self.db.write(batch)?;
+        log::info!("Indexed {} entries", self.count);
+        Ok(())
+    }
+
+    /// Sort the pending entries of the family, combine all values for each key and write them
+    /// to an SST file which is then moved into the database.
+    fn ingest(&mut self, family: &str) -> Result<()> {
+        let mut run = self.runs.remove(family).unwrap_or_default();
+        if run.is_empty() {
+            return Ok(());
+        }
+        run.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let scratch = self.scratch.as_ref().ok_or_else(|| anyhow!("Not loading in bulk"))?;
+        let path = scratch.path().join(format!("bulk-{}-{}.sst", family, self.written));
+        self.written += 1;
+        let opts = family_options(self.metadata, family);
+        let mut writer = SstFileWriter::create(&opts);
+        writer.open(&path)?;
+        let mut entries = run.into_iter().peekable();
+        while let Some((key, mut value)) = entries.next() {
+            while let Some((_, next)) = entries.next_if(|(next, _)| *next == key) {
+                value.extend(next);
+            }
+            writer.merge(key, value)?;
+        }
+        writer.finish()?;
+
+        let mut ingest_opts = IngestExternalFileOptions::default();
+        ingest_opts.set_move_files(true);
+        self.db.ingest_external_file_cf_opts(self.family(family)?, &ingest_opts, vec![&path])?;
+        if path.exists() {
+            std::fs::remove_file(&path)?;
         }
+        log::info!("Indexed {} entries", self.count);
+        Ok(())
+    }
+
+    /// Write any remaining entries to the database.
+    fn finish(mut self) -> Result<()> {
+        self.commit()?;
+        let families: Vec<String> = self.runs.keys().cloned().collect();
+        for family in families {
+            self.ingest(&family)?;
+        }
+        Ok(())
     }
-    Some(result)
 }
 
-pub fn index(spec: &Spec, data_type: &str, filename: &Path) -> anyhow::Result<()> {
-    let mut reader = rnc_utils::buf_reader(&filename)?;
+/// Read the metadata of the index at the given path, without opening all column families. This
+/// is None if there is no index, or if it was written before metadata was stored.
+fn read_metadata(path: &Path) -> Result<Option<Metadata>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let opts = Options::default();
+    if !DB::list_cf(&opts, path)?.iter().any(|name| name == METADATA_FAMILY) {
+        return Ok(None);
+    }
+    let db = DB::open_cf_for_read_only(&opts, path, [METADATA_FAMILY], false)?;
+    Metadata::read(&db)
+}
+
+/// Check if the column family holds indexed data, rather than being the default family, which
+/// is unused, or the one holding the metadata.
+fn is_data_family(name: &str) -> bool {
+    name != "default" && name != METADATA_FAMILY
+}
 
+fn family_options(metadata: &Metadata, name: &str) -> Options {
+    let mut cf_opts = Options::default();
+    metadata.merge_policy(name).set_operator(&mut cf_opts);
+    if let Some(compression) = metadata.compression.get(name) {
+        cf_opts.set_compression_type(compression.db_type());
+    }
+    cf_opts
+}
+
+fn family_descriptor(metadata: &Metadata, name: &str) -> ColumnFamilyDescriptor {
+    match is_data_family(name) {
+        true => ColumnFamilyDescriptor::new(name, family_options(metadata, name)),
+        false => ColumnFamilyDescriptor::new(name, Options::default()),
+    }
+}
+
+/// Open, or create, the index for writing the given column families, creating any of them
+/// which do not exist yet. This returns the metadata which describes how to write values.
+fn open_for_writing(spec: &Spec, families: &[String]) -> Result<(DB, Metadata)> {
     let mut db_opts = Options::default();
     db_opts.create_if_missing(true);
-    db_opts.set_merge_operator("append operator", concat_merge, None);
-
-    let mut families: Vec<String> = Vec::new();
-    let mut store = match spec.path.exists() {
-        true => {
-            families.extend(DB::list_cf(&db_opts, spec.path)?);
-
-            match families.len() {
-                0 | 1 => {
-                    println!("BASIC OPEN");
-                    DB::open(&db_opts, spec.path)
-                },
-                _ => {
-                    println!("OPEN WITH CF");
-                    let descriptors = families.clone().into_iter().map(|name| {
-                        let mut cf_opts = Options::default();
-                        cf_opts.set_merge_operator("append operator", concat_merge, None);
-                        ColumnFamilyDescriptor::new(name, cf_opts)
-                    });
-                    DB::open_cf_descriptors(&db_opts, spec.path, descriptors)
-                },
-            }
-        },
-        false => DB::open(&db_opts, spec.path),
-    }?;
+    db_opts.create_missing_column_families(true);
 
-    if !families.contains(&data_type.to_string()) {
-        store.create_cf(data_type, &db_opts)?;
+    if let Some(name) = families.iter().find(|name| !is_data_family(name)) {
+        return Err(anyhow!("Cannot write data to the reserved column family {}", name));
     }
-    let family = store.cf_handle(data_type).unwrap();
 
-    let mut buf = String::new();
-    loop {
-        match reader.read_line(&mut buf)? {
-            0 => break,
-            _ => {
-                let line = buf.replace("\\\\", "\\");
-                let id: DocId = serde_json::from_str::<Query<DocId>>(&line)?.into();
-                store.merge_cf(&family, id.id.as_bytes(), line.as_bytes())?;
-                buf.clear();
-            },
+    let mut names: Vec<String> = families.to_vec();
+    let existing = match spec.path.exists() {
+        true => DB::list_cf(&db_opts, spec.path)?,
+        false => Vec::new(),
+    };
+    let has_data = existing.iter().any(|name| is_data_family(name));
+    names.extend(existing);
+    names.push(METADATA_FAMILY.to_string());
+    names.sort();
+    names.dedup();
+
+    let mut metadata = match (read_metadata(spec.path)?, has_data) {
+        (Some(metadata), _) => metadata,
+        (None, true) => Metadata::default(),
+        (None, false) => Metadata::current(),
+    };
+    if let Some(compression) = spec.compression {
+        for name in families {
+            metadata.compression.insert(name.to_string(), compression);
+        }
+    }
+    if let Some(codec) = spec.key_codec {
+        if has_data && codec != metadata.key_codec {
+            return Err(anyhow!(
+                "Index stores keys as {:?}, cannot change to {:?}",
+                metadata.key_codec,
+                codec
+            ));
+        }
+        metadata.key_codec = codec;
+    }
+    if let Some(merge) = spec.merge {
+        if merge.requires_frames() && metadata.format()? != Format::Framed {
+            return Err(anyhow!("Merge policy {:?} is not supported by this index format", merge));
+        }
+        for name in families {
+            metadata.merge.insert(name.to_string(), merge);
         }
     }
 
-    Ok(())
+    let descriptors = names.iter().map(|name| family_descriptor(&metadata, name));
+    let db = DB::open_cf_descriptors(&db_opts, spec.path, descriptors)?;
+    metadata.write(&db)?;
+    Ok((db, metadata))
 }
 
-fn path_as_column_name(path: &Path) -> String {
-    path.file_stem().unwrap().to_str().unwrap().to_string()
+/// Parse a line of a file being indexed, producing the encoded key and value.
+fn parse_line(
+    spec: &Spec,
+    metadata: &Metadata,
+    format: Format,
+    line: &str,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let document: Value = serde_json::from_str(line)?;
+    let mut id = spec.key.extract(&document)?;
+    if let Some(validation) = spec.validation {
+        id = validation.normalize(&id)?;
+    }
+    let id = metadata.key_codec.encode(&id)?;
+    Ok((id, format.encode(line, &document)?))
+}
+
+/// A message from the threads reading the files being indexed.
+enum Parsed {
+    /// The column family, encoded key and encoded value of a valid line.
+    Entry(String, Vec<u8>, Vec<u8>),
+    /// A line which could not be indexed, and should be written to the quarantine file.
+    Invalid(String),
 }
 
-fn send_file_lines(path: &Path, sender: Sender<(String, DocId, String)>) {
-    let file = File::open(path).unwrap();
-    let data_type = path_as_column_name(&path);
-    let mut reader = BufReader::new(file);
+/// Read and parse every line of the file, sending each one to be written. Invalid lines are
+/// either quarantined, if the spec allows it, or stop the indexing with an error giving the
+/// file and line number.
+fn send_file_lines(
+    spec: &Spec,
+    metadata: &Metadata,
+    format: Format,
+    family: &str,
+    path: &Path,
+    failed: &AtomicBool,
+    sender: &Sender<Parsed>,
+) -> Result<()> {
+    let mut reader = rnc_utils::buf_reader(path)
+        .with_context(|| format!("Could not open {}", path.display()))?;
     let mut buf = String::new();
+    let mut line_number = 0;
     loop {
-        match reader.read_line(&mut buf).unwrap() {
-            0 => break,
-            _ => {
-                let line = buf.replace("\\\\", "\\");
-                let id: DocId = serde_json::from_str::<Query<DocId>>(&line).unwrap().into();
-                sender.send((data_type.to_string(), id, line.to_string())).unwrap();
-                buf.clear();
+        if failed.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let read = reader
+            .read_line(&mut buf)
+            .with_context(|| format!("Could not read {}:{}", path.display(), line_number + 1))?;
+        if read == 0 {
+            break;
+        }
+        line_number += 1;
+
+        let line = buf.replace("\\\\", "\\");
+        let parsed = match (parse_line(spec, metadata, format, &line), spec.quarantine) {
+            (Ok((id, value)), _) => Parsed::Entry(family.to_string(), id, value),
+            (Err(err), Some(_)) => {
+                log::warn!("Quarantining {}:{}, {}", path.display(), line_number, err);
+                // The line as it was read, so the quarantine file can be fixed and indexed again.
+                Parsed::Invalid(buf.clone())
+            },
+            (Err(err), None) => {
+                return Err(err.context(format!("Invalid line {}:{}", path.display(), line_number)))
             },
+        };
+        if sender.send(parsed).is_err() {
+            return Ok(());
         }
+        buf.clear();
     }
+
+    log::info!("Read {} lines from {}", line_number, path.display());
+    Ok(())
 }
 
+/// Index each of the files into the given column family. The files are read and parsed by a
+/// pool of `threads` workers, while all writes happen in the calling thread.
+fn index_paths(spec: &Spec, files: Vec<(String, PathBuf)>) -> Result<()> {
+    let families: Vec<String> = files.iter().map(|(family, _)| family.to_string()).collect();
+    let (store, metadata) = open_for_writing(spec, &families)?;
+    let format = metadata.format()?;
+    let mut quarantine = match spec.quarantine {
+        Some(path) => Some(rnc_utils::buf_writer(path)?),
+        None => None,
+    };
+
+    let (file_sender, file_receiver) = unbounded();
+    for file in files {
+        file_sender.send(file)?;
+    }
+    drop(file_sender);
+
+    let failed = AtomicBool::new(false);
+    crossbeam_utils::thread::scope(|scope| {
+        let (sender, receiver) = bounded(spec.batch_size);
+        let workers: Vec<_> = (0..spec.threads)
+            .map(|_| {
+                let files = file_receiver.clone();
+                let sender = sender.clone();
+                let failed = &failed;
+                let metadata = &metadata;
+                scope.spawn(move |_| -> Result<()> {
+                    for (family, path) in files {
+                        let result = send_file_lines(
+                            spec, metadata, format, &family, &path, failed, &sender,
+                        );
+                        if result.is_err() {
+                            failed.store(true, Ordering::Relaxed);
+                        }
+                        result?;
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+        drop(sender);
+
+        let mut loader = Loader::new(spec, &store, &metadata)?;
+        let written = receiver.iter().try_for_each(|parsed| match (parsed, &mut quarantine) {
+            (Parsed::Entry(family, id, value), _) => loader.add(&family, id, value),
+            (Parsed::Invalid(line), Some(writer)) => Ok(writer.write_all(line.as_bytes())?),
+            (Parsed::Invalid(_), None) => Err(anyhow!("Invalid line without a quarantine file")),
+        });
+        if written.is_err() {
+            failed.store(true, Ordering::Relaxed);
+        }
+        drop(receiver);
+
+        let mut result = written;
+        for worker in workers {
+            let read = worker.join().map_err(|_| anyhow!("An indexing thread panicked"))?;
+            if result.is_ok() {
+                result = read;
+            }
+        }
+        result?;
+        loader.finish()
+    })
+    .map_err(|_| anyhow!("An indexing thread panicked"))??;
+
+    if let Some(writer) = quarantine {
+        writer.finish()?;
+    }
+    Ok(())
+}
+
+pub fn index(spec: &Spec, data_type: &str, filename: &Path) -> anyhow::Result<()> {
+    index_paths(spec, vec![(data_type.to_string(), filename.to_path_buf())])
+}
+
+fn path_as_column_name(path: &Path) -> anyhow::Result<String> {
+    let stripped = match rnc_utils::Compression::from_path(path) {
+        rnc_utils::Compression::None => path.to_path_buf(),
+        _ => path.with_extension(""),
+    };
+    let name = stripped
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| anyhow!("Cannot name a column family after {}", path.display()))?;
+    Ok(name.to_string())
+}
+
+/// Index each file listed in the given file, one per line, into a column family named after
+/// the file. Blank lines are skipped.
 pub fn index_files(spec: &Spec, filename: &Path) -> anyhow::Result<()> {
     let reader = rnc_utils::buf_reader(&filename)?;
-    let (json_sender, json_reciever) = unbounded();
-    let mut families: Vec<String> = Vec::new();
+    let mut files = Vec::new();
     for line in reader.lines() {
         let line = line?;
-        let filename = line.trim_end();
-        let path = PathBuf::from(filename);
-        families.push(path_as_column_name(&path));
-        let sender = json_sender.clone();
-        thread::spawn(move || send_file_lines(&path, sender));
+        let raw = line.trim_end();
+        if raw.is_empty() {
+            continue;
+        }
+        let path = PathBuf::from(raw);
+        files.push((path_as_column_name(&path)?, path));
     }
+    index_paths(spec, files)
+}
 
-    let mut db_opts = Options::default();
-    db_opts.create_if_missing(true);
-    db_opts.set_merge_operator("append operator", concat_merge, None);
-
-    let store = match spec.path.exists() {
-        true => {
-            families.extend(DB::list_cf(&db_opts, spec.path)?);
-            families.sort();
-            families.dedup();
-
-            match families.len() {
-                0 | 1 => DB::open(&db_opts, spec.path),
-                _ => {
-                    let descriptors = families.clone().into_iter().map(|name| {
-                        let mut cf_opts = Options::default();
-                        cf_opts.set_merge_operator("append operator", concat_merge, None);
-                        ColumnFamilyDescriptor::new(name, cf_opts)
-                    });
-                    DB::open_cf_descriptors(&db_opts, spec.path, descriptors)
-                },
+/// How to open an existing index.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Access {
+    /// Only read the index, this does not take the lock on the index so many processes can
+    /// read it at once. Data written after the index is opened is not visible.
+    ReadOnly,
+    /// Open as a RocksDB secondary instance, which can read an index while another process is
+    /// still writing to it. The path is a directory for this instance to keep its own logs in.
+    Secondary(PathBuf),
+    /// Read and modify the index, only a single process can do this at once.
+    ReadWrite,
+}
+
+/// A handle to an existing index. This opens the database once and can then be used to fetch
+/// the values stored under any key, either in a single column family or across all of them.
+pub struct Store {
+    db: DB,
+    families: Vec<String>,
+    format: Format,
+    metadata: Metadata,
+}
+
+/// A summary of the contents of a single column family. The sizes and counts come from RocksDB
+/// properties and so are estimates.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FamilyStats {
+    pub estimated_keys: u64,
+    pub live_data_size: u64,
+    pub sst_files_size: u64,
+    pub memtable_size: u64,
+    pub merge_policy: MergePolicy,
+    pub compression: Option<Compression>,
+    /// The first keys in the family along with the size in bytes of the value stored for each.
+    pub sample: Vec<(String, usize)>,
+}
+
+/// A summary of the contents of an index, see `Store::stats`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Stats {
+    pub format_version: u32,
+    pub release: Option<String>,
+    pub created: Option<u64>,
+    pub families: BTreeMap<String, FamilyStats>,
+}
+
+impl Store {
+    /// Open the index stored at the given path read-only. This will fail if there is no index
+    /// there. Any number of processes may read the same index this way at once.
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::open_with(path, &Access::ReadOnly)
+    }
+
+    /// Open the index stored at the given path with the given access.
+    pub fn open_with(path: &Path, access: &Access) -> Result<Self> {
+        let db_opts = Options::default();
+        let names = DB::list_cf(&db_opts, path)?;
+        let metadata = read_metadata(path)?.unwrap_or_default();
+        let descriptors = names.iter().map(|name| family_descriptor(&metadata, name));
+        let db = match access {
+            Access::ReadOnly => {
+                DB::open_cf_descriptors_read_only(&db_opts, path, descriptors, false)?
+            },
+            Access::Secondary(secondary) => {
+                let db = DB::open_cf_descriptors_as_secondary(
+                    &db_opts,
+                    path,
+                    secondary.as_path(),
+                    descriptors,
+                )?;
+                db.try_catch_up_with_primary()?;
+                db
+            },
+            Access::ReadWrite => DB::open_cf_descriptors(&db_opts, path, descriptors)?,
+        };
+        let families = names.into_iter().filter(|name| is_data_family(name)).collect();
+        let format = metadata.format()?;
+
+        Ok(Self {
+            db,
+            families,
+            format,
+            metadata,
+        })
+    }
+
+    /// Make data written by the primary since the index was opened visible. This only works
+    /// for indexes opened as a secondary instance.
+    pub fn catch_up(&self) -> Result<()> {
+        self.db.try_catch_up_with_primary()?;
+        Ok(())
+    }
+
+    /// Summarize what is in each column family of the index, including the first `sample` keys
+    /// of each family.
+    pub fn stats(&self, sample: usize) -> Result<Stats> {
+        let mut families = BTreeMap::new();
+        for name in &self.families {
+            let family = self.family(name)?;
+            let property = |property: &str| -> Result<u64> {
+                Ok(self.db.property_int_value_cf(family, property)?.unwrap_or(0))
+            };
+
+            let mut keys = Vec::with_capacity(sample);
+            let mut iter = self.db.raw_iterator_cf(family);
+            iter.seek_to_first();
+            while keys.len() < sample && iter.valid() {
+                if let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+                    keys.push((self.metadata.key_codec.decode(key)?, value.len()));
+                }
+                iter.next();
             }
+            iter.status()?;
+
+            families.insert(name.to_string(), FamilyStats {
+                estimated_keys: property("rocksdb.estimate-num-keys")?,
+                live_data_size: property("rocksdb.estimate-live-data-size")?,
+                sst_files_size: property("rocksdb.total-sst-files-size")?,
+                memtable_size: property("rocksdb.cur-size-all-mem-tables")?,
+                merge_policy: self.metadata.merge_policy(name),
+                compression: self.metadata.compression.get(name).copied(),
+                sample: keys,
+            });
+        }
+
+        Ok(Stats {
+            format_version: self.metadata.format_version,
+            release: self.metadata.release.clone(),
+            created: self.metadata.created,
+            families,
+        })
+    }
+
+    /// The names of all column families, that is the data types, which have been indexed.
+    pub fn families(&self) -> &[String] {
+        &self.families
+    }
+
+    /// How keys are stored in the index, this decides which keys are valid.
+    pub fn key_codec(&self) -> KeyCodec {
+        self.metadata.key_codec
+    }
+
+    fn family(&self, name: &str) -> Result<&ColumnFamily> {
+        self.db
+            .cf_handle(name)
+            .filter(|_| is_data_family(name))
+            .ok_or_else(|| anyhow!("Unknown column family {}", name))
+    }
+
+    /// Delete all data for the keys from each of the given column families. This only writes
+    /// tombstones, the space is not reclaimed until the families are compacted.
+    pub fn delete(&self, families: &[String], keys: &[String]) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        for name in families {
+            let family = self.family(name)?;
+            for key in keys {
+                batch.delete_cf(family, self.metadata.key_codec.encode(key)?);
+            }
+        }
+        self.db.write(batch)?;
+        Ok(())
+    }
+
+    /// Compact all data in the given column families, which removes deleted data from disk.
+    pub fn compact(&self, families: &[String]) -> Result<()> {
+        for name in families {
+            compact_family(&self.db, self.family(name)?);
+        }
+        Ok(())
+    }
+
+    /// Create a checkpoint of the index in the given directory, which must not exist. This is a
+    /// complete copy of the index which shares the files it can with the original by hard
+    /// linking them, so it is cheap as long as both are on the same filesystem.
+    pub fn checkpoint(&self, dest: &Path) -> Result<()> {
+        Checkpoint::new(&self.db)?.create_checkpoint(dest)?;
+        Ok(())
+    }
+
+    /// Record the release, if given, and the current time in the metadata of the index.
+    pub fn record_release(&mut self, release: Option<&str>) -> Result<()> {
+        self.metadata.record_release(release)?;
+        self.metadata.write(&self.db)
+    }
+
+    /// Remove a column family, and all data in it, from the index. The settings stored for the
+    /// family, like the merge policy, are removed as well.
+    pub fn drop_family(&mut self, name: &str) -> Result<()> {
+        self.family(name)?;
+        self.db.drop_cf(name)?;
+        self.families.retain(|family| family != name);
+        self.metadata.compression.remove(name);
+        self.metadata.merge.remove(name);
+        self.metadata.write(&self.db)
+    }
+
+    /// Get all values stored for the key in the given column family. If the key has no data in
+    /// the column family this is empty.
+    pub fn get(&self, family: &str, key: &str) -> Result<Vec<Value>> {
+        let key = self.metadata.key_codec.encode(key)?;
+        match self.db.get_pinned_cf(self.family(family)?, key)? {
+            None => Ok(Vec::new()),
+            Some(raw) => self.format.decode(&raw),
+        }
+    }
+
+    /// Get all data for the key as a single JSON object. This has an 'id' entry which is the key
+    /// and then one entry per column family, which is the list of all values in that family. If
+    /// the key has no data in any family then this returns None.
+    pub fn grouped(&self, key: &str) -> Result<Option<Map<String, Value>>> {
+        let mut data = Map::new();
+        data.insert(String::from("id"), Value::String(key.to_string()));
+        let mut seen = false;
+        for name in &self.families {
+            let values = self.get(name, key)?;
+            seen |= !values.is_empty();
+            data.insert(name.to_string(), Value::Array(values));
+        }
+
+        match seen {
+            true => Ok(Some(data)),
+            false => Ok(None),
+        }
+    }
+
+    /// Get the grouped data, as from `grouped`, for many keys at once. The results are in the
+    /// same order as the keys. This fetches all keys from all column families with a single
+    /// `multi_get_cf` call, which is much faster than fetching each key on its own.
+    pub fn grouped_batch(&self, keys: &[String]) -> Result<Vec<Option<Map<String, Value>>>> {
+        self.selected_batch(&Selection::default(), keys)
+    }
+
+    /// Get the grouped data for many keys at once, like `grouped_batch`, but only including
+    /// the selected families and fields. A key which has no data in any selected family is
+    /// None.
+    pub fn selected_batch(
+        &self,
+        selection: &Selection,
+        keys: &[String],
+    ) -> Result<Vec<Option<Map<String, Value>>>> {
+        let families = selection.families(&self.families)?;
+        if families.is_empty() {
+            return Ok(vec![None; keys.len()]);
+        }
+
+        let handles = families
+            .iter()
+            .map(|name| self.family(name))
+            .collect::<Result<Vec<&ColumnFamily>>>()?;
+        let encoded = keys
+            .iter()
+            .map(|key| self.metadata.key_codec.encode(key))
+            .collect::<Result<Vec<Vec<u8>>>>()?;
+        let requests = encoded
+            .iter()
+            .flat_map(|key| handles.iter().map(move |handle| (handle, key.as_slice())));
+        let responses = self.db.multi_get_cf(requests);
+
+        let mut found = Vec::with_capacity(keys.len());
+        for (key, values) in keys.iter().zip(responses.chunks(families.len())) {
+            let mut data = Map::new();
+            data.insert(String::from("id"), Value::String(key.to_string()));
+            let mut seen = false;
+            for (name, value) in families.iter().zip(values) {
+                let values = match value {
+                    Ok(Some(raw)) => self.format.decode(raw)?,
+                    Ok(None) => Vec::new(),
+                    Err(err) => return Err(err.clone().into()),
+                };
+                seen |= !values.is_empty();
+                let values = values.into_iter().map(|v| selection.project(name, v)).collect();
+                data.insert(name.to_string(), Value::Array(values));
+            }
+            found.push(match seen {
+                true => Some(data),
+                false => None,
+            });
+        }
+
+        Ok(found)
+    }
+
+    /// Get the grouped data, like `selected_batch`, of every key which starts with each of the
+    /// prefixes. The results are in the same order as the prefixes and each contains the data
+    /// for the matching keys in key order, which is empty if there are none. With
+    /// `KeyCodec::UrsTaxid` keys a URS prefix finds the data for all of its taxids.
+    pub fn selected_prefixes(
+        &self,
+        selection: &Selection,
+        prefixes: &[String],
+    ) -> Result<Vec<Vec<Map<String, Value>>>> {
+        let families = selection.families(&self.families)?;
+        let mut found = Vec::with_capacity(prefixes.len());
+        for prefix in prefixes {
+            let range = KeyRange {
+                prefix: Some(prefix.to_string()),
+                start: None,
+                end: None,
+            };
+            let keys = self
+                .scan(&families, &range)?
+                .map(|entry| entry.map(|(key, _)| key))
+                .collect::<Result<Vec<String>>>()?;
+            found.push(self.selected_batch(selection, &keys)?.into_iter().flatten().collect());
+        }
+        Ok(found)
+    }
+
+    /// Iterate over every key, in key order, which has data in any column family. Each key is
+    /// produced once no matter how many families contain it.
+    pub fn keys(&self) -> impl Iterator<Item = Result<String>> + '_ {
+        let scan = Scan::new(&self.db, self.metadata.key_codec, &self.families, Bounds::default());
+        scan.map(|entry| entry.map(|(key, _)| key))
+    }
+
+    /// Scan, in key order, the keys of the given column families which fall in the range. Each
+    /// key is produced once along with the number of the scanned families which contain it.
+    pub fn scan<'a>(&'a self, families: &[String], range: &KeyRange) -> Result<Scan<'a>> {
+        for name in families {
+            self.family(name)?;
+        }
+        let bounds = range.encode(self.metadata.key_codec)?;
+        Ok(Scan::new(&self.db, self.metadata.key_codec, families, bounds))
+    }
+
+    /// Iterate over all keys, in key order, of the given column family along with the values
+    /// stored for each one.
+    pub fn iter<'a>(
+        &'a self,
+        family: &str,
+    ) -> Result<impl Iterator<Item = Result<(String, Vec<Value>)>> + 'a> {
+        let iter = self.db.iterator_cf(self.family(family)?, IteratorMode::Start);
+        let format = self.format;
+        let codec = self.metadata.key_codec;
+        Ok(iter.map(move |(key, value)| {
+            let key = codec.decode(&key)?;
+            Ok((key, format.decode(&value)?))
+        }))
+    }
+}
+
+/// A limit on the keys visited when scanning an index. Keys must start with the prefix, be
+/// greater than or equal to the start and less than the end, if each is given. In an index
+/// with `KeyCodec::UrsTaxid` keys each limit may also be a URS on its own, see
+/// `KeyCodec::encode_prefix`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct KeyRange {
+    pub prefix: Option<String>,
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+impl KeyRange {
+    /// Convert each limit of the range to the stored form of the keys.
+    fn encode(&self, codec: KeyCodec) -> Result<Bounds> {
+        let encode = |key: &Option<String>| key.as_deref().map(|k| codec.encode_prefix(k));
+        Ok(Bounds {
+            prefix: encode(&self.prefix).transpose()?,
+            start: encode(&self.start).transpose()?,
+            end: encode(&self.end).transpose()?,
+        })
+    }
+}
+
+/// A `KeyRange` with each limit encoded as it is stored in the index.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Bounds {
+    prefix: Option<Vec<u8>>,
+    start: Option<Vec<u8>>,
+    end: Option<Vec<u8>>,
+}
+
+impl Bounds {
+    fn seek_key(&self) -> Option<&[u8]> {
+        match (&self.prefix, &self.start) {
+            (Some(prefix), Some(start)) => Some(std::cmp::max(prefix, start).as_slice()),
+            (Some(key), None) | (None, Some(key)) => Some(key.as_slice()),
+            (None, None) => None,
+        }
+    }
+
+    /// Check if a key, which must be at or after the seek key, is past the end of the range.
+    fn is_past(&self, key: &[u8]) -> bool {
+        if let Some(end) = &self.end {
+            if key >= end.as_slice() {
+                return true;
+            }
+        }
+        match &self.prefix {
+            Some(prefix) => !key.starts_with(prefix),
+            None => false,
+        }
+    }
+}
+
+/// An iterator over the keys of several column families in an index, see `Store::scan`.
+pub struct Scan<'a> {
+    codec: KeyCodec,
+    bounds: Bounds,
+    iterators: Vec<DBRawIterator<'a>>,
+}
+
+impl<'a> Scan<'a> {
+    fn new(db: &'a DB, codec: KeyCodec, families: &[String], bounds: Bounds) -> Self {
+        let iterators = families
+            .iter()
+            .map(|name| {
+                let mut iter = db.raw_iterator_cf(db.cf_handle(name).unwrap());
+                match bounds.seek_key() {
+                    Some(key) => iter.seek(key),
+                    None => iter.seek_to_first(),
+                }
+                iter
+            })
+            .collect();
+        Self {
+            codec,
+            bounds,
+            iterators,
+        }
+    }
+}
+
+impl<'a> Iterator for Scan<'a> {
+    type Item = Result<(String, usize)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for iter in &self.iterators {
+            if let Err(err) = iter.status() {
+                return Some(Err(err.into()));
+            }
+        }
+
+        let current = self.iterators.iter().filter_map(|i| i.key()).min()?.to_vec();
+        if self.bounds.is_past(&current) {
+            return None;
+        }
+
+        let mut count = 0;
+        for iter in self.iterators.iter_mut() {
+            if iter.key() == Some(current.as_slice()) {
+                count += 1;
+                iter.next();
+            }
+        }
+
+        Some(self.codec.decode(&current).map(|key| (key, count)))
+    }
+}
+
+/// How to combine the column families when dumping an index.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Join {
+    /// Only keys which have data in all of the selected families.
+    Inner,
+    /// Keys which have data in any of the selected families.
+    Outer,
+}
+
+impl FromStr for Join {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        match raw {
+            "inner" => Ok(Join::Inner),
+            "outer" => Ok(Join::Outer),
+            _ => Err(anyhow!("Unknown join type {}, must be 'inner' or 'outer'", raw)),
+        }
+    }
+}
+
+/// Fetch the data for a batch of keys, or of key prefixes if the spec asks for them. Each key
+/// has a list of the entries found for it.
+fn fetch_batch(spec: &Spec, store: &Store, keys: &[String]) -> Result<Vec<Vec<Row>>> {
+    match spec.prefix {
+        true => store.selected_prefixes(&spec.selection, keys),
+        false => Ok(store
+            .selected_batch(&spec.selection, keys)?
+            .into_iter()
+            .map(|data| data.into_iter().collect())
+            .collect()),
+    }
+}
+
+/// Lookup a block of keys by splitting it into one batch per thread and fetching all batches in
+/// parallel. The data is written in the same order as the keys.
+fn write_block(
+    spec: &Spec,
+    store: &Store,
+    keys: &[String],
+    writer: &mut dyn RowWriter,
+) -> Result<()> {
+    let size = keys.len() / spec.threads + 1;
+    let results = crossbeam_utils::thread::scope(|scope| {
+        let workers: Vec<_> = keys
+            .chunks(size)
+            .map(|batch| scope.spawn(move |_| fetch_batch(spec, store, batch)))
+            .collect();
+        workers.into_iter().map(|worker| worker.join()).collect::<Vec<_>>()
+    })
+    .map_err(|_| anyhow!("A lookup thread panicked"))?;
+
+    let mut rows = Vec::with_capacity(keys.len());
+    let mut keys = keys.iter();
+    for result in results {
+        let found = result.map_err(|_| anyhow!("A lookup thread panicked"))??;
+        for (entries, key) in found.into_iter().zip(keys.by_ref()) {
+            match (entries.is_empty(), spec.allow_missing) {
+                (false, _) => rows.extend(entries),
+                (true, true) => log::warn!("No data found for key {}", key),
+                (true, false) => return Err(anyhow!("No data found for key {}", key)),
+            }
+        }
+    }
+
+    writer.write_rows(&rows)
+}
+
+/// Write the grouped data for each key in the key file. Keys are read in blocks and each block
+/// is looked up with several threads, each fetching a batch of keys at a time.
+pub fn lookup(spec: &Spec, key_file: &Path, output: &Path) -> Result<()> {
+    let store = Store::open_with(spec.path, &spec.access())?;
+    let families = spec.selection.families(store.families())?;
+    let mut out = rnc_utils::buf_writer(&output)?;
+    let mut writer = spec.output_format.writer(Box::new(&mut out), families, &spec.selection)?;
+    let keys = rnc_utils::buf_reader(&key_file)?;
+    let block_size = spec.threads * spec.batch_size;
+    let mut block = Vec::with_capacity(block_size);
+
+    for key in keys.lines() {
+        // A blank line would be looked up as the empty key, which as a prefix matches every key.
+        let key = key?;
+        let key = key.trim_end();
+        if key.is_empty() {
+            continue;
+        }
+        block.push(key.to_string());
+        if block.len() == block_size {
+            write_block(spec, &store, &block, writer.as_mut())?;
+            block.clear();
+        }
+    }
+    write_block(spec, &store, &block, writer.as_mut())?;
+
+    writer.finish()?;
+    out.finish()?;
+    Ok(())
+}
+
+/// Write a summary, as JSON, of the contents of each column family in the index.
+pub fn stats(spec: &Spec, sample: usize, output: &Path) -> Result<()> {
+    let store = Store::open_with(spec.path, &spec.access())?;
+    let mut writer = rnc_utils::buf_writer(output)?;
+    serde_json::to_writer_pretty(&mut writer, &store.stats(sample)?)?;
+    writeln!(&mut writer)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Write all data for a single key as pretty printed JSON. If a family is given only the values
+/// in that family are written, otherwise this is the grouped data as produced by `lookup`.
+pub fn get(spec: &Spec, key: &str, family: Option<&str>, output: &Path) -> Result<()> {
+    let store = Store::open_with(spec.path, &spec.access())?;
+    let data = match family {
+        Some(family) => Value::Array(store.get(family, key)?),
+        None => match store.grouped(key)? {
+            Some(data) => Value::Object(data),
+            None => return Err(anyhow!("No data found for key {}", key)),
         },
-        false => DB::open(&db_opts, spec.path),
-    }?;
+    };
+    let mut writer = rnc_utils::buf_writer(output)?;
+    serde_json::to_writer_pretty(&mut writer, &data)?;
+    writeln!(&mut writer)?;
+    writer.finish()?;
+    Ok(())
+}
 
-    let mut column_map: HashMap<String, &ColumnFamily> = HashMap::new();
-    for name in families {
-        column_map.insert(name.clone(), store.cf_handle(&name).unwrap());
+/// Delete all data for each key in the key file from the given column families, or from all of
+/// them if none are given. The families are compacted afterwards to reclaim the space.
+pub fn delete(spec: &Spec, families: &[String], key_file: &Path) -> Result<()> {
+    let store = Store::open_with(spec.path, &Access::ReadWrite)?;
+    let families = match families.is_empty() {
+        true => store.families().to_vec(),
+        false => families.to_vec(),
+    };
+    let keys = rnc_utils::buf_reader(key_file)?;
+    let mut block = Vec::new();
+    let mut count = 0;
+
+    for key in keys.lines() {
+        // Not every key codec can encode the empty key, so a blank line would fail the delete.
+        let key = key?;
+        let key = key.trim_end();
+        if key.is_empty() {
+            continue;
+        }
+        block.push(key.to_string());
+        if block.len() == spec.commit_size {
+            store.delete(&families, &block)?;
+            count += block.len();
+            block.clear();
+        }
     }
+    store.delete(&families, &block)?;
+    count += block.len();
+    log::info!("Deleted {} keys from {}", count, families.join(", "));
 
-    for (data_type, id, data) in json_reciever {
-        let column = column_map[&data_type];
-        store.merge_cf(column, id.id.as_bytes(), data.as_bytes())?;
+    store.compact(&families)
+}
+
+/// Remove the given column families, and all their data, from the index.
+pub fn drop_families(spec: &Spec, families: &[String]) -> Result<()> {
+    let mut store = Store::open_with(spec.path, &Access::ReadWrite)?;
+    for name in families {
+        store.drop_family(name)?;
+        log::info!("Dropped column family {}", name);
     }
+    Ok(())
+}
 
+/// Create a checkpoint of the index at the destination, then record the release and creation
+/// time in the metadata of the checkpoint. The original index is not changed, and is opened
+/// read-only so a checkpoint can be made while another process is writing to it.
+pub fn checkpoint(spec: &Spec, dest: &Path, release: Option<&str>) -> Result<()> {
+    let store = Store::open_with(spec.path, &Access::ReadOnly)?;
+    store.checkpoint(dest)?;
+    drop(store);
+
+    let mut copy = Store::open_with(dest, &Access::ReadWrite)?;
+    copy.record_release(release)?;
+    log::info!("Created checkpoint of {} in {}", spec.path.display(), dest.display());
     Ok(())
 }
 
-pub fn lookup(spec: &Spec, key_file: &Path, output: &Path) -> anyhow::Result<()> {
-    let mut cfs: Vec<(String, &ColumnFamily)> = Vec::new();
-    let mut db_opts = Options::default();
-    db_opts.set_merge_operator("append operator", concat_merge, None);
-    let names = DB::list_cf(&db_opts, spec.path)?;
-    let descriptors = names.clone().into_iter().map(|name| {
-        let mut cf_opts = Options::default();
-        cf_opts.set_merge_operator("append operator", concat_merge, None);
-        ColumnFamilyDescriptor::new(name, cf_opts)
-    });
-    let store = DB::open_cf_descriptors(&db_opts, spec.path, descriptors)?;
+/// Compact all of a column family. By default RocksDB does not rewrite files which are already
+/// in the bottommost level, which is where most of the data is, so this forces it to. Otherwise
+/// a new compression would never reach that data.
+fn compact_family(db: &DB, family: &ColumnFamily) {
+    let mut options = CompactOptions::default();
+    options.set_bottommost_level_compaction(BottommostLevelCompaction::Force);
+    db.compact_range_cf_opt(family, None::<&[u8]>, None::<&[u8]>, &options);
+}
 
-    for name in names {
-        if name != "default" {
-            cfs.push((name.to_string(), store.cf_handle(&name).unwrap()));
+/// Compact the given column families, or all of them if none are given. If the spec has a
+/// compression it is stored for the families and the compaction rewrites their data with it.
+/// The release and the time of the compaction are recorded in the metadata.
+pub fn compact(spec: &Spec, families: &[String], release: Option<&str>) -> Result<()> {
+    let existing: Vec<String> = DB::list_cf(&Options::default(), spec.path)?
+        .into_iter()
+        .filter(|name| is_data_family(name))
+        .collect();
+    for name in families {
+        if !existing.contains(name) {
+            return Err(anyhow!("Unknown column family {}", name));
         }
     }
+    let families = match families.is_empty() {
+        true => existing,
+        false => families.to_vec(),
+    };
 
-    let mut writer = rnc_utils::buf_writer(&output)?;
-    let mut keys = rnc_utils::buf_reader(&key_file)?;
-    let mut buf = String::new();
+    let (db, mut metadata) = open_for_writing(spec, &families)?;
+    for name in &families {
+        let family = db.cf_handle(name).ok_or_else(|| anyhow!("Unknown column family {}", name))?;
+        compact_family(&db, family);
+        log::info!("Compacted column family {}", name);
+    }
+    metadata.record_release(release)?;
+    metadata.write(&db)
+}
 
-    loop {
-        match keys.read_line(&mut buf)? {
-            0 => break,
-            _ => {
-                let mut data = HashMap::new();
-                let trimmed = buf.trim_end();
-                data.insert("id", serde_json::Value::String(trimmed.to_string()));
-                let mut seen = false;
-                let key = trimmed.as_bytes();
-                for (name, cf) in &cfs {
-                    let default = serde_json::Value::Array(Vec::new());
-                    let to_update = data.entry(name).or_insert(default).as_array_mut().unwrap();
-                    match store.get_pinned_cf(cf, &key)? {
-                        None => (),
-                        Some(v) => {
-                            seen = true;
-                            let text = str::from_utf8(&v)?;
-                            let values = Deserializer::from_str(text).into_iter::<Value>();
-                            for value in values {
-                                to_update.push(value?);
-                            }
-                        },
-                    }
-                }
+/// Serve the index read-only over HTTP on the given host and port until the process is stopped,
+/// see `Server` for what it answers.
+#[cfg(feature = "server")]
+pub fn serve(spec: &Spec, host: &str, port: u16) -> Result<()> {
+    let store = Store::open_with(spec.path, &spec.access())?;
+    let server = Server::bind(store, &format!("{}:{}", host, port))?;
+    log::info!("Serving {} on http://{}", spec.path.display(), server.address()?);
+    server.run()
+}
 
-                match (seen, spec.allow_missing) {
-                    (true, _) => {
-                        data.remove("__sled__default");
-                    },
-                    (false, true) => {
-                        log::warn!("No data found for key {}", &buf);
-                        continue;
-                    },
-                    (false, false) => {
-                        return Err(anyhow::anyhow!("No data found for key {}", &buf));
-                    },
-                }
+/// Write the grouped data, as produced by `lookup`, for every key in the range in key order. Keys
+/// are selected from the given column families, or all of them if none are given, using the join
+/// to decide if a key must be in all of them or just one of them.
+pub fn dump(
+    spec: &Spec,
+    range: &KeyRange,
+    families: &[String],
+    join: Join,
+    output: &Path,
+) -> Result<()> {
+    let store = Store::open_with(spec.path, &spec.access())?;
+    let families = match families.is_empty() {
+        true => store.families().to_vec(),
+        false => families.to_vec(),
+    };
+    let mut writer = rnc_utils::buf_writer(&output)?;
 
-                serde_json::to_writer(&mut writer, &data)?;
-                writeln!(&mut writer)?;
-                buf.clear();
-            },
+    for entry in store.scan(&families, range)? {
+        let (key, count) = entry?;
+        if join == Join::Inner && count != families.len() {
+            continue;
+        }
+
+        if let Some(data) = store.grouped(&key)? {
+            serde_json::to_writer(&mut writer, &data)?;
+            writeln!(&mut writer)?;
         }
     }
 
+    writer.finish()?;
     Ok(())
 }
@@ -0,0 +1,97 @@
+use std::error::Error;
+
+use serde_json::json;
+
+use rocksdb::{
+    ColumnFamilyDescriptor,
+    Options,
+    DB,
+};
+
+use rnc_test_utils::temp_file_with;
+
+use tempfile::tempdir;
+
+use kv::{
+    encoding::{
+        Compression,
+        Format,
+    },
+    metadata::{
+        Metadata,
+        METADATA_FAMILY,
+    },
+    store::{
+        self,
+        Spec,
+        Store,
+    },
+};
+
+#[test]
+fn can_decode_concatenated_values() -> Result<(), Box<dyn Error>> {
+    let first = json!({"id": "a", "value": 1.5, "tags": ["x", null]});
+    let second = json!({"id": "a", "value": -2});
+    for format in &[Format::Json, Format::Framed] {
+        let mut raw = format.encode(&format!("{}\n", first), &first)?;
+        raw.extend(format.encode(&format!("{}\n", second), &second)?);
+        assert_eq!(format.decode(&raw)?, vec![first.clone(), second.clone()]);
+        assert_eq!(Format::from_version(format.version())?, *format);
+    }
+
+    let truncated = Format::Framed.encode("", &first)?;
+    assert!(Format::Framed.decode(&truncated[..truncated.len() - 1]).is_err());
+    assert!(Format::from_version(3).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn can_read_indexes_without_metadata() -> Result<(), Box<dyn Error>> {
+    let db_path = tempdir()?.into_path().join("index.db");
+    {
+        let mut db_opts = Options::default();
+        db_opts.create_if_missing(true);
+        db_opts.create_missing_column_families(true);
+        let descriptors = vec![ColumnFamilyDescriptor::new("hits", Options::default())];
+        let db = DB::open_cf_descriptors(&db_opts, &db_path, descriptors)?;
+        let hits = db.cf_handle("hits").unwrap();
+        db.put_cf(hits, b"a", b"{\"id\": \"a\", \"v\": 1}\n{\"id\": \"a\", \"v\": 2}\n")?;
+    }
+
+    let store = Store::open(&db_path)?;
+    assert_eq!(
+        store.get("hits", "a")?,
+        vec![json!({"id": "a", "v": 1}), json!({"id": "a", "v": 2})]
+    );
+    drop(store);
+
+    let extra = temp_file_with(vec![r#"{"id": "a", "v": 3}"#])?;
+    store::index(&Spec::new(&db_path), "hits", extra.path())?;
+    let store = Store::open(&db_path)?;
+    assert_eq!(store.get("hits", "a")?.len(), 3);
+
+    Ok(())
+}
+
+#[test]
+fn new_indexes_record_format_and_compression() -> Result<(), Box<dyn Error>> {
+    let db_path = tempdir()?.into_path().join("index.db");
+    let hits = temp_file_with(vec![r#"{"id": "a", "v": 1}"#])?;
+    let mut spec = Spec::new(&db_path);
+    spec.set_compression(Some(Compression::Zstd));
+    store::index(&spec, "hits", hits.path())?;
+
+    {
+        let opts = Options::default();
+        let db = DB::open_cf_for_read_only(&opts, &db_path, [METADATA_FAMILY], false)?;
+        let metadata = Metadata::read(&db)?.unwrap();
+        assert_eq!(metadata.format()?, Format::Framed);
+        assert_eq!(metadata.compression.get("hits"), Some(&Compression::Zstd));
+    }
+
+    let store = Store::open(&db_path)?;
+    assert_eq!(store.get("hits", "a")?, vec![json!({"id": "a", "v": 1})]);
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use serde_json::json;
+
+use kv::key::{
+    Key,
+    KeyCodec,
+    KeyValidation,
+};
+
+#[test]
+fn can_extract_simple_keys() {
+    let key = Key::default();
+    assert_eq!(key.extract(&json!({"id": "a", "urs": "b"})).unwrap(), "a");
+    assert_eq!(key.extract(&json!({"id": 10})).unwrap(), "10");
+    assert!(key.extract(&json!({"urs": "b"})).is_err());
+    assert!(key.extract(&json!({"id": null})).is_err());
+}
+
+#[test]
+fn can_extract_nested_and_joined_keys() {
+    let doc = json!({"urs": "URS0000000001", "taxid": 9606, "hits": [{"model": "RF00001"}]});
+    assert_eq!(
+        "urs,.taxid".parse::<Key>().unwrap().extract(&doc).unwrap(),
+        "URS0000000001_9606"
+    );
+    assert_eq!(".hits.0.model".parse::<Key>().unwrap().extract(&doc).unwrap(), "RF00001");
+    assert!(".hits.1.model".parse::<Key>().unwrap().extract(&doc).is_err());
+}
+
+#[test]
+fn rejects_empty_paths() {
+    assert!("".parse::<Key>().is_err());
+    assert!("urs,".parse::<Key>().is_err());
+    assert!("a..b".parse::<Key>().is_err());
+    assert_eq!("urs, taxid".parse::<Key>().unwrap().to_string(), ".urs,.taxid");
+}
+
+#[test]
+fn can_encode_urs_taxid_keys() {
+    let codec = KeyCodec::UrsTaxid;
+    let encoded = codec.encode("URS0000614226_291828").unwrap();
+    assert_eq!(encoded.len(), 16);
+    assert_eq!(codec.decode(&encoded).unwrap(), "URS0000614226_291828");
+    assert!(codec.encode("URS0000614226_9").unwrap() < codec.encode("URS0000614226_10").unwrap());
+    assert_eq!(codec.encode_prefix("URS0000614226").unwrap(), &encoded[..8]);
+    assert!(codec.encode("URS0000614226").is_err());
+    assert!(codec.encode("a").is_err());
+    assert_eq!(KeyCodec::Text.encode("a").unwrap(), b"a");
+}
+
+#[test]
+fn can_normalize_keys() {
+    let urs = KeyValidation::Urs;
+    assert_eq!(urs.normalize("urs0000614226 ").unwrap(), "URS0000614226");
+    assert_eq!(urs.normalize("URS614226").unwrap(), "URS0000614226");
+    assert!(urs.normalize("xURS0000614226").is_err());
+    assert!(urs.normalize("URS00000614226A").is_err());
+    assert!(urs.normalize("URS0000614226_9606").is_err());
+
+    let urs_taxid = KeyValidation::UrsTaxid;
+    assert_eq!(urs_taxid.normalize("urs0000614226_09606\n").unwrap(), "URS0000614226_9606");
+    assert!(urs_taxid.normalize("URS0000614226").is_err());
+    assert!(urs_taxid.normalize("URS0000614226_").is_err());
+    assert!(urs_taxid.normalize("URS0000614226_human").is_err());
+}
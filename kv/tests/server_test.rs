@@ -0,0 +1,116 @@
+#![cfg(feature = "server")]
+
+use std::{
+    error::Error,
+    io::{
+        Read,
+        Write,
+    },
+    net::{
+        SocketAddr,
+        TcpStream,
+    },
+    path::PathBuf,
+};
+
+use serde_json::{
+    json,
+    Value,
+};
+
+use rnc_test_utils::temp_file_with;
+
+use tempfile::tempdir;
+
+use kv::{
+    key::KeyCodec,
+    server::Server,
+    store::{
+        self,
+        Spec,
+        Store,
+    },
+};
+
+fn serve_hits(codec: KeyCodec, hits: Vec<&str>) -> Result<SocketAddr, Box<dyn Error>> {
+    let hits = temp_file_with(hits)?;
+    let db_path: PathBuf = tempdir()?.into_path().join("index.db");
+    let mut spec = Spec::new(&db_path);
+    spec.set_key_codec(Some(codec));
+    store::index(&spec, "hits", hits.path())?;
+
+    let server = Server::bind(Store::open(&db_path)?, "127.0.0.1:0")?;
+    let address = server.address()?;
+    std::thread::spawn(move || server.run());
+    Ok(address)
+}
+
+fn example_server() -> Result<SocketAddr, Box<dyn Error>> {
+    serve_hits(KeyCodec::Text, vec![
+        r#"{"id": "a", "value": 1}"#,
+        r#"{"id": "b", "value": 2}"#,
+        r#"{"id": "a b", "value": 3}"#,
+    ])
+}
+
+/// Send a request and return the status code and the JSON body of the response.
+fn request(
+    address: &SocketAddr,
+    method: &str,
+    path: &str,
+    body: &str,
+) -> Result<(u16, Value), Box<dyn Error>> {
+    let mut stream = TcpStream::connect(address)?;
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        body.len(),
+        body
+    )?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+
+    let status = response.split(' ').nth(1).ok_or("Missing status")?.parse()?;
+    let (_, body) = response.split_once("\r\n\r\n").ok_or("Missing body")?;
+    Ok((status, serde_json::from_str(body)?))
+}
+
+#[test]
+fn can_serve_an_index() -> Result<(), Box<dyn Error>> {
+    let address = example_server()?;
+
+    assert_eq!(request(&address, "GET", "/families", "")?, (200, json!(["hits"])));
+    assert_eq!(
+        request(&address, "GET", "/key/a", "")?,
+        (200, json!({"id": "a", "hits": [{"id": "a", "value": 1}]}))
+    );
+    assert_eq!(request(&address, "GET", "/key/missing", "")?.0, 404);
+    assert_eq!(
+        request(&address, "GET", "/key/a%20b", "")?,
+        (200, json!({"id": "a b", "hits": [{"id": "a b", "value": 3}]}))
+    );
+    assert_eq!(request(&address, "GET", "/key/%FF", "")?.0, 400);
+    assert_eq!(
+        request(&address, "POST", "/lookup", r#"["b", "missing"]"#)?,
+        (200, json!([{"id": "b", "hits": [{"id": "b", "value": 2}]}, null]))
+    );
+    assert_eq!(request(&address, "POST", "/lookup", "b")?.0, 400);
+    assert_eq!(request(&address, "POST", "/key/a", "")?.0, 405);
+    assert_eq!(request(&address, "GET", "/other", "")?.0, 404);
+
+    Ok(())
+}
+
+#[test]
+fn rejects_keys_the_index_cannot_store() -> Result<(), Box<dyn Error>> {
+    let address = serve_hits(KeyCodec::UrsTaxid, vec![r#"{"id": "URS0000000001_9606", "v": 1}"#])?;
+
+    assert_eq!(request(&address, "GET", "/key/URS0000000001_9606", "")?.0, 200);
+    assert_eq!(request(&address, "GET", "/key/URS0000000002_9606", "")?.0, 404);
+    assert_eq!(request(&address, "GET", "/key/URS0000000001", "")?.0, 400);
+    assert_eq!(request(&address, "POST", "/lookup", r#"["URS0000000001_9606", "a"]"#)?.0, 400);
+
+    Ok(())
+}
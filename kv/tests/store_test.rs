@@ -0,0 +1,261 @@
+use std::{
+    error::Error,
+    io,
+    path::PathBuf,
+};
+
+use serde_json::json;
+
+use rnc_test_utils::temp_file_with;
+
+use tempfile::tempdir;
+
+use kv::{
+    key::KeyCodec,
+    merge::MergePolicy,
+    store::{
+        self,
+        Access,
+        KeyRange,
+        Spec,
+        Store,
+    },
+};
+
+fn temp_index_dir() -> io::Result<PathBuf> {
+    let out_file = tempdir()?;
+    let mut out_path = out_file.into_path();
+    out_path.push("index.db");
+    Ok(out_path)
+}
+
+fn example_index() -> Result<PathBuf, Box<dyn Error>> {
+    let hits = temp_file_with(vec![
+        r#"{"id": "a", "value": "1"}"#,
+        r#"{"id": "b", "value": "2"}"#,
+        r#"{"id": "a", "value": "3"}"#,
+    ])?;
+    let base = temp_file_with(vec![r#"{"id": "b", "length": 10}"#, r#"{"id": "c", "length": 2}"#])?;
+    let db_path = temp_index_dir()?;
+    let spec = Spec::new(&db_path);
+    store::index(&spec, "hits", hits.path())?;
+    store::index(&spec, "base", base.path())?;
+    Ok(db_path)
+}
+
+#[test]
+fn can_get_values_from_each_family() -> Result<(), Box<dyn Error>> {
+    let db_path = example_index()?;
+    let store = Store::open(&db_path)?;
+
+    let mut families = store.families().to_vec();
+    families.sort();
+    assert_eq!(families, vec!["base", "hits"]);
+    assert_eq!(
+        store.get("hits", "a")?,
+        vec![json!({"id": "a", "value": "1"}), json!({"id": "a", "value": "3"})]
+    );
+    assert_eq!(store.get("base", "a")?, Vec::<serde_json::Value>::new());
+    assert!(store.get("unknown", "a").is_err());
+    assert!(store.get("metadata", "a").is_err());
+    drop(store);
+
+    let data = temp_file_with(vec![r#"{"id": "a"}"#])?;
+    assert!(store::index(&Spec::new(&db_path), "metadata", data.path()).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn can_get_grouped_values() -> Result<(), Box<dyn Error>> {
+    let db_path = example_index()?;
+    let store = Store::open(&db_path)?;
+
+    assert_eq!(
+        store.grouped("b")?.map(serde_json::Value::Object),
+        Some(json!({
+            "id": "b",
+            "base": [{"id": "b", "length": 10}],
+            "hits": [{"id": "b", "value": "2"}],
+        }))
+    );
+    assert_eq!(store.grouped("missing")?, None);
+
+    Ok(())
+}
+
+#[test]
+fn can_iterate_over_a_family() -> Result<(), Box<dyn Error>> {
+    let db_path = example_index()?;
+    let store = Store::open(&db_path)?;
+
+    let entries = store.iter("hits")?.collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(
+        entries,
+        vec![
+            (
+                String::from("a"),
+                vec![json!({"id": "a", "value": "1"}), json!({"id": "a", "value": "3"})]
+            ),
+            (String::from("b"), vec![json!({"id": "b", "value": "2"})]),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn can_iterate_over_all_keys() -> Result<(), Box<dyn Error>> {
+    let db_path = example_index()?;
+    let store = Store::open(&db_path)?;
+
+    let keys = store.keys().collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(keys, vec!["a", "b", "c"]);
+
+    Ok(())
+}
+
+#[test]
+fn can_get_many_grouped_values() -> Result<(), Box<dyn Error>> {
+    let db_path = example_index()?;
+    let store = Store::open(&db_path)?;
+
+    let keys = vec![String::from("c"), String::from("missing"), String::from("a")];
+    let found = store.grouped_batch(&keys)?;
+    let expected = keys.iter().map(|k| store.grouped(k)).collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(found, expected);
+    assert_eq!(found[1], None);
+
+    Ok(())
+}
+
+#[test]
+fn batched_and_bulk_indexing_agree() -> Result<(), Box<dyn Error>> {
+    let hits = temp_file_with(vec![
+        r#"{"id": "c", "value": "1"}"#,
+        r#"{"id": "a", "value": "2"}"#,
+        r#"{"id": "c", "value": "3"}"#,
+        r#"{"id": "b", "value": "4"}"#,
+        r#"{"id": "c", "value": "5"}"#,
+    ])?;
+    let expected = json!({
+        "id": "c",
+        "hits": [
+            {"id": "c", "value": "1"},
+            {"id": "c", "value": "3"},
+            {"id": "c", "value": "5"},
+        ],
+    });
+
+    for bulk in &[false, true] {
+        let db_path = temp_index_dir()?;
+        let mut spec = Spec::new(&db_path);
+        spec.set_commit_size(2);
+        spec.set_bulk(*bulk);
+        store::index(&spec, "hits", hits.path())?;
+
+        let parent = db_path.parent().unwrap();
+        let left: Vec<_> = std::fs::read_dir(parent)?.collect::<Result<_, _>>()?;
+        assert_eq!(left.iter().map(|e| e.file_name()).collect::<Vec<_>>(), vec!["index.db"]);
+
+        let store = Store::open(&db_path)?;
+        assert_eq!(store.keys().collect::<Result<Vec<_>, _>>()?, vec!["a", "b", "c"]);
+        assert_eq!(store.grouped("c")?.map(serde_json::Value::Object), Some(expected.clone()));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn merge_policies_are_remembered() -> Result<(), Box<dyn Error>> {
+    let first = temp_file_with(vec![r#"{"id": "a", "value": 1}"#, r#"{"id": "a", "value": 2}"#])?;
+    let second = temp_file_with(vec![r#"{"id": "a", "value": 2}"#, r#"{"id": "a", "value": 3}"#])?;
+
+    let db_path = temp_index_dir()?;
+    let mut spec = Spec::new(&db_path);
+    spec.set_merge_policy(Some(MergePolicy::Union));
+    store::index(&spec, "union", first.path())?;
+    spec.set_merge_policy(Some(MergePolicy::Replace));
+    store::index(&spec, "replace", first.path())?;
+    spec.set_merge_policy(None);
+    store::index(&spec, "append", first.path())?;
+
+    let spec = Spec::new(&db_path);
+    for family in &["union", "replace", "append"] {
+        store::index(&spec, family, second.path())?;
+    }
+
+    let store = Store::open(&db_path)?;
+    let values = |family| -> Result<Vec<serde_json::Value>, Box<dyn Error>> {
+        Ok(store.get(family, "a")?.into_iter().map(|v| v["value"].clone()).collect())
+    };
+    assert_eq!(values("union")?, vec![json!(1), json!(2), json!(3)]);
+    assert_eq!(values("replace")?, vec![json!(3)]);
+    assert_eq!(values("append")?, vec![json!(1), json!(2), json!(2), json!(3)]);
+
+    Ok(())
+}
+
+#[test]
+fn can_open_an_index_in_each_mode() -> Result<(), Box<dyn Error>> {
+    let db_path = example_index()?;
+    let keys = vec![String::from("a")];
+
+    let first = Store::open(&db_path)?;
+    let second = Store::open(&db_path)?;
+    assert_eq!(first.get("hits", "a")?, second.get("hits", "a")?);
+    assert!(first.delete(&[String::from("hits")], &keys).is_err());
+    drop(first);
+    drop(second);
+
+    let secondary = Store::open_with(&db_path, &Access::Secondary(tempdir()?.into_path()))?;
+    let writer = Store::open_with(&db_path, &Access::ReadWrite)?;
+    writer.delete(&[String::from("hits")], &keys)?;
+    writer.compact(&[String::from("hits")])?;
+    drop(writer);
+    assert_eq!(secondary.get("hits", "a")?.len(), 2);
+    secondary.catch_up()?;
+    assert_eq!(secondary.get("hits", "a")?, Vec::<serde_json::Value>::new());
+
+    Ok(())
+}
+
+#[test]
+fn can_index_with_urs_taxid_keys() -> Result<(), Box<dyn Error>> {
+    let data = temp_file_with(vec![
+        r#"{"id": "URS0000000002_10", "value": 1}"#,
+        r#"{"id": "URS0000000002_9", "value": 2}"#,
+        r#"{"id": "URS0000000001_9606", "value": 3}"#,
+        r#"{"id": "URS0000000003_1", "value": 4}"#,
+    ])?;
+    let db_path = temp_index_dir()?;
+    let mut spec = Spec::new(&db_path);
+    spec.set_key_codec(Some(KeyCodec::UrsTaxid));
+    store::index(&spec, "hits", data.path())?;
+
+    let mut text = Spec::new(&db_path);
+    text.set_key_codec(Some(KeyCodec::Text));
+    assert!(store::index(&text, "hits", data.path()).is_err());
+
+    let store = Store::open(&db_path)?;
+    assert_eq!(store.get("hits", "URS0000000002_9")?.len(), 1);
+    assert!(store.get("hits", "a").is_err());
+    assert_eq!(store.keys().collect::<Result<Vec<_>, _>>()?, vec![
+        "URS0000000001_9606",
+        "URS0000000002_9",
+        "URS0000000002_10",
+        "URS0000000003_1"
+    ]);
+
+    let range = KeyRange {
+        prefix: Some(String::from("URS0000000002")),
+        start: None,
+        end: None,
+    };
+    let families = vec![String::from("hits")];
+    let keys: Vec<String> =
+        store.scan(&families, &range)?.map(|e| e.map(|(k, _)| k)).collect::<Result<_, _>>()?;
+    assert_eq!(keys, vec!["URS0000000002_9", "URS0000000002_10"]);
+
+    Ok(())
+}
@@ -133,3 +133,582 @@ fn lookup_grouped_test() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+fn dump(db_path: &Path, args: &[&str]) -> io::Result<Output> {
+    test_bin::get_test_bin("kv").arg("dump").args(args).arg(db_path).arg("-").output()
+}
+
+fn dump_index() -> Result<PathBuf, Box<dyn Error>> {
+    let first = temp_file_with(vec![
+        r#"{"id": "a1", "value": "1"}"#,
+        r#"{"id": "a2", "value": "2"}"#,
+        r#"{"id": "b1", "value": "3"}"#,
+    ])?;
+    let second = temp_file_with(vec![
+        r#"{"id": "a2", "other": "4"}"#,
+        r#"{"id": "c1", "other": "5"}"#,
+    ])?;
+    let db_dir = temp_index_dir()?;
+    assert_eq!(index("first", first.path(), &db_dir)?.status.success(), true);
+    assert_eq!(index("second", second.path(), &db_dir)?.status.success(), true);
+    Ok(db_dir)
+}
+
+#[test]
+fn dump_all_keys_test() -> Result<(), Box<dyn Error>> {
+    let db_dir = dump_index()?;
+    let query = dump(&db_dir, &[])?;
+    assert_eq!(String::from_utf8_lossy(&query.stderr), "");
+    assert_eq!(
+        query.jsonl()?,
+        vec![
+            json!({"id": "a1", "first": [{"id": "a1", "value": "1"}], "second": []}),
+            json!({
+                "id": "a2",
+                "first": [{"id": "a2", "value": "2"}],
+                "second": [{"id": "a2", "other": "4"}],
+            }),
+            json!({"id": "b1", "first": [{"id": "b1", "value": "3"}], "second": []}),
+            json!({"id": "c1", "first": [], "second": [{"id": "c1", "other": "5"}]}),
+        ]
+    );
+    assert_eq!(query.status.success(), true);
+
+    Ok(())
+}
+
+#[test]
+fn dump_prefix_and_range_test() -> Result<(), Box<dyn Error>> {
+    let db_dir = dump_index()?;
+
+    let query = dump(&db_dir, &["--prefix", "a"])?;
+    let ids: Vec<_> = query.jsonl()?.into_iter().map(|v| v["id"].clone()).collect();
+    assert_eq!(ids, vec![json!("a1"), json!("a2")]);
+
+    let query = dump(&db_dir, &["--start", "a2", "--end", "c1"])?;
+    let ids: Vec<_> = query.jsonl()?.into_iter().map(|v| v["id"].clone()).collect();
+    assert_eq!(ids, vec![json!("a2"), json!("b1")]);
+
+    Ok(())
+}
+
+#[test]
+fn dump_joins_test() -> Result<(), Box<dyn Error>> {
+    let db_dir = dump_index()?;
+
+    let query = dump(&db_dir, &["--families", "first,second", "--join", "inner"])?;
+    let ids: Vec<_> = query.jsonl()?.into_iter().map(|v| v["id"].clone()).collect();
+    assert_eq!(ids, vec![json!("a2")]);
+
+    let query = dump(&db_dir, &["--families", "second"])?;
+    let ids: Vec<_> = query.jsonl()?.into_iter().map(|v| v["id"].clone()).collect();
+    assert_eq!(ids, vec![json!("a2"), json!("c1")]);
+
+    let query = dump(&db_dir, &["--families", "missing"])?;
+    assert_eq!(query.status.success(), false);
+
+    Ok(())
+}
+
+#[test]
+fn parallel_lookup_keeps_order_test() -> Result<(), Box<dyn Error>> {
+    let lines: Vec<String> =
+        (0..50).map(|i| format!(r#"{{"id": "{}", "value": {}}}"#, i, i)).collect();
+    let data_file = temp_file_with(lines.iter().map(|l| l.as_str()).collect())?;
+    let db_dir = temp_index_dir()?;
+    assert_eq!(index("example", data_file.path(), &db_dir)?.status.success(), true);
+
+    let ids: Vec<String> =
+        (0..50).rev().map(|i| i.to_string()).chain(vec!["missing".into()]).collect();
+    let id_file = temp_file_with(ids.iter().map(|i| i.as_str()).collect())?;
+    let query = test_bin::get_test_bin("kv")
+        .arg("lookup")
+        .args(&["--allow-missing", "--threads", "3", "--batch-size", "2"])
+        .arg(&db_dir)
+        .arg(id_file.path())
+        .arg("-")
+        .output()?;
+
+    let expected: Vec<_> = (0..50)
+        .rev()
+        .map(|i| json!({"id": i.to_string(), "example": [{"id": i.to_string(), "value": i}]}))
+        .collect();
+    assert_eq!(query.jsonl()?, expected);
+    assert_eq!(query.status.success(), true);
+
+    Ok(())
+}
+
+#[test]
+fn index_with_custom_key_test() -> Result<(), Box<dyn Error>> {
+    let data_file = temp_file_with(vec![
+        r#"{"urs": "URS0000000001", "taxid": 9606, "model": "RF00001"}"#,
+        r#"{"urs": "URS0000000001", "taxid": 562, "model": "RF00002"}"#,
+    ])?;
+    let db_dir = temp_index_dir()?;
+    let result = test_bin::get_test_bin("kv")
+        .arg("index")
+        .args(&["--key", "urs,taxid"])
+        .arg("rfam_hits")
+        .arg(data_file.path())
+        .arg(&db_dir)
+        .output()?;
+    assert_eq!(String::from_utf8_lossy(&result.stderr), "");
+    assert_eq!(result.status.success(), true);
+
+    let id_file = temp_file_with(vec!["URS0000000001_562"])?;
+    let query = lookup(id_file.path(), &db_dir, &PathBuf::from("-"))?;
+    assert_eq!(
+        query.jsonl()?,
+        vec![json!({"id": "URS0000000001_562", "rfam_hits": [
+            {"urs": "URS0000000001", "taxid": 562, "model": "RF00002"},
+        ]})]
+    );
+
+    let result = index("rfam_hits", data_file.path(), &temp_index_dir()?)?;
+    assert_eq!(result.status.success(), false);
+
+    Ok(())
+}
+
+#[test]
+fn stats_and_get_test() -> Result<(), Box<dyn Error>> {
+    let db_dir = dump_index()?;
+
+    let stats =
+        test_bin::get_test_bin("kv").args(&["stats", "--sample", "2"]).arg(&db_dir).output()?;
+    assert_eq!(String::from_utf8_lossy(&stats.stderr), "");
+    assert_eq!(stats.status.success(), true);
+    let stats: serde_json::Value = serde_json::from_slice(&stats.stdout)?;
+    assert_eq!(stats["format_version"], json!(2));
+    let mut families: Vec<&String> = stats["families"].as_object().unwrap().keys().collect();
+    families.sort();
+    assert_eq!(families, vec!["first", "second"]);
+    assert_eq!(stats["families"]["first"]["sample"].as_array().unwrap().len(), 2);
+    assert_eq!(stats["families"]["first"]["merge_policy"], json!("append"));
+
+    let get = test_bin::get_test_bin("kv").arg("get").arg(&db_dir).arg("a2").output()?;
+    assert_eq!(get.status.success(), true);
+    let data: serde_json::Value = serde_json::from_slice(&get.stdout)?;
+    assert_eq!(data["id"], json!("a2"));
+    assert_eq!(data["second"], json!([{"id": "a2", "other": "4"}]));
+
+    let get = test_bin::get_test_bin("kv").arg("get").arg(&db_dir).arg("missing").output()?;
+    assert_eq!(get.status.success(), false);
+
+    Ok(())
+}
+
+fn index_files(file_list: &Path, db_path: &Path, args: &[&str]) -> io::Result<Output> {
+    test_bin::get_test_bin("kv").arg("index-files").args(args).arg(file_list).arg(db_path).output()
+}
+
+#[test]
+fn index_files_test() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let hits = dir.path().join("hits.json");
+    std::fs::write(&hits, "{\"id\": \"a\", \"value\": 1}\n{\"id\": \"b\", \"value\": 2}\n")?;
+    let base = dir.path().join("base.json");
+    std::fs::write(&base, "{\"id\": \"a\", \"length\": 3}\n")?;
+    let file_list =
+        temp_file_with(vec![hits.to_str().unwrap(), "", base.to_str().unwrap(), "  "])?;
+    let db_dir = temp_index_dir()?;
+
+    let result = index_files(file_list.path(), &db_dir, &["--threads", "2"])?;
+    assert_eq!(String::from_utf8_lossy(&result.stderr), "");
+    assert_eq!(result.status.success(), true);
+
+    let id_file = temp_file_with(vec!["a"])?;
+    let query = lookup(id_file.path(), &db_dir, &PathBuf::from("-"))?;
+    assert_eq!(
+        query.jsonl()?,
+        vec![json!({
+            "id": "a",
+            "base": [{"id": "a", "length": 3}],
+            "hits": [{"id": "a", "value": 1}],
+        })]
+    );
+
+    let file_list = temp_file_with(vec![hits.to_str().unwrap(), ".."])?;
+    let result = index_files(file_list.path(), &temp_index_dir()?, &[])?;
+    assert_eq!(result.status.success(), false);
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(stderr.contains("Cannot name a column family after .."), "{}", stderr);
+
+    Ok(())
+}
+
+#[test]
+fn index_files_reports_bad_lines_test() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let hits = dir.path().join("hits.json");
+    std::fs::write(&hits, "{\"id\": \"a\"}\nnot \\\\ json\n{\"value\": 2}\n{\"id\": \"b\"}\n")?;
+    let file_list = temp_file_with(vec![hits.to_str().unwrap()])?;
+
+    let result = index_files(file_list.path(), &temp_index_dir()?, &[])?;
+    assert_eq!(result.status.success(), false);
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(stderr.contains(&format!("{}:2", hits.display())), "{}", stderr);
+
+    let quarantine = dir.path().join("bad.json");
+    let db_dir = temp_index_dir()?;
+    let result =
+        index_files(file_list.path(), &db_dir, &["--quarantine", quarantine.to_str().unwrap()])?;
+    assert_eq!(result.status.success(), true);
+    // Lines are quarantined as they were read, before any unescaping.
+    assert_eq!(std::fs::read_to_string(&quarantine)?, "not \\\\ json\n{\"value\": 2}\n");
+
+    let id_file = temp_file_with(vec!["a", "b"])?;
+    let query = lookup(id_file.path(), &db_dir, &PathBuf::from("-"))?;
+    assert_eq!(query.jsonl()?.len(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn delete_and_drop_cf_test() -> Result<(), Box<dyn Error>> {
+    let db_dir = dump_index()?;
+
+    let keys = temp_file_with(vec!["a1", "a2"])?;
+    let result = test_bin::get_test_bin("kv")
+        .arg("delete")
+        .args(&["--families", "first"])
+        .arg(&db_dir)
+        .arg(keys.path())
+        .output()?;
+    assert_eq!(String::from_utf8_lossy(&result.stderr), "");
+    assert_eq!(result.status.success(), true);
+
+    let query = dump(&db_dir, &[])?;
+    let ids: Vec<serde_json::Value> = query.jsonl()?.into_iter().map(|v| v["id"].clone()).collect();
+    assert_eq!(ids, vec![json!("a2"), json!("b1"), json!("c1")]);
+
+    let result = test_bin::get_test_bin("kv").arg("drop-cf").arg(&db_dir).arg("second").output()?;
+    assert_eq!(result.status.success(), true);
+    let query = dump(&db_dir, &[])?;
+    assert_eq!(query.jsonl()?, vec![json!({"id": "b1", "first": [{"id": "b1", "value": "3"}]})]);
+
+    let result = test_bin::get_test_bin("kv").arg("drop-cf").arg(&db_dir).arg("second").output()?;
+    assert_eq!(result.status.success(), false);
+
+    Ok(())
+}
+
+#[test]
+fn delete_skips_blank_lines_test() -> Result<(), Box<dyn Error>> {
+    let data_file = temp_file_with(vec![
+        r#"{"id": "URS0000000001_9606", "value": 1}"#,
+        r#"{"id": "URS0000000002_9606", "value": 2}"#,
+    ])?;
+    let db_dir = temp_index_dir()?;
+    let result = test_bin::get_test_bin("kv")
+        .args(&["index", "--key-codec", "urs-taxid", "hits"])
+        .arg(data_file.path())
+        .arg(&db_dir)
+        .output()?;
+    assert_eq!(result.status.success(), true);
+
+    // The urs-taxid codec cannot encode the empty key.
+    let keys = temp_file_with(vec!["URS0000000001_9606", "", "  "])?;
+    let result = test_bin::get_test_bin("kv").arg("delete").arg(&db_dir).arg(keys.path()).output()?;
+    assert_eq!(String::from_utf8_lossy(&result.stderr), "");
+    assert_eq!(result.status.success(), true);
+
+    let query = dump(&db_dir, &[])?;
+    let ids: Vec<serde_json::Value> = query.jsonl()?.into_iter().map(|v| v["id"].clone()).collect();
+    assert_eq!(ids, vec![json!("URS0000000002_9606")]);
+
+    Ok(())
+}
+
+#[test]
+fn lookup_selection_test() -> Result<(), Box<dyn Error>> {
+    let db_dir = dump_index()?;
+    let id_file = temp_file_with(vec!["a2", "b1"])?;
+    let select = |args: &[&str]| {
+        test_bin::get_test_bin("kv")
+            .arg("lookup")
+            .arg("--allow-missing")
+            .args(args)
+            .arg(&db_dir)
+            .arg(id_file.path())
+            .arg("-")
+            .output()
+    };
+
+    let query = select(&["--only", "second"])?;
+    assert_eq!(query.jsonl()?, vec![json!({"id": "a2", "second": [{"id": "a2", "other": "4"}]})]);
+
+    let query = select(&["--exclude", "second", "--fields", "first=value"])?;
+    assert_eq!(
+        query.jsonl()?,
+        vec![
+            json!({"id": "a2", "first": [{"value": "2"}]}),
+            json!({"id": "b1", "first": [{"value": "3"}]}),
+        ]
+    );
+
+    let query = select(&["--only", "missing"])?;
+    assert_eq!(query.status.success(), false);
+    let query = select(&["--fields", "first"])?;
+    assert_eq!(query.status.success(), false);
+
+    Ok(())
+}
+
+#[test]
+fn lookup_by_prefix_test() -> Result<(), Box<dyn Error>> {
+    let data_file = temp_file_with(vec![
+        r#"{"id": "URS0000000002_10", "value": 1}"#,
+        r#"{"id": "URS0000000002_9", "value": 2}"#,
+        r#"{"id": "URS0000000001_9606", "value": 3}"#,
+    ])?;
+    let db_dir = temp_index_dir()?;
+    let result = test_bin::get_test_bin("kv")
+        .args(&["index", "--key-codec", "urs-taxid", "hits"])
+        .arg(data_file.path())
+        .arg(&db_dir)
+        .output()?;
+    assert_eq!(String::from_utf8_lossy(&result.stderr), "");
+    assert_eq!(result.status.success(), true);
+
+    let id_file = temp_file_with(vec!["URS0000000002", "", "  ", "URS0000000003"])?;
+    let query = test_bin::get_test_bin("kv")
+        .args(&["lookup", "--prefix", "--allow-missing"])
+        .arg(&db_dir)
+        .arg(id_file.path())
+        .arg("-")
+        .output()?;
+    assert_eq!(query.status.success(), true);
+    assert_eq!(query.jsonl()?, vec![
+        json!({"id": "URS0000000002_9", "hits": [{"id": "URS0000000002_9", "value": 2}]}),
+        json!({"id": "URS0000000002_10", "hits": [{"id": "URS0000000002_10", "value": 1}]}),
+    ]);
+
+    Ok(())
+}
+
+#[test]
+fn checkpoint_and_compact_test() -> Result<(), Box<dyn Error>> {
+    let db_dir = dump_index()?;
+    let dir = tempdir()?;
+    let copy_dir = dir.path().join("copy.db");
+    let stats = |path: &Path| -> Result<serde_json::Value, Box<dyn Error>> {
+        let output = test_bin::get_test_bin("kv").arg("stats").arg(path).output()?;
+        Ok(serde_json::from_slice(&output.stdout)?)
+    };
+
+    let result = test_bin::get_test_bin("kv")
+        .args(&["checkpoint", "--release", "21"])
+        .arg(&db_dir)
+        .arg(&copy_dir)
+        .output()?;
+    assert_eq!(String::from_utf8_lossy(&result.stderr), "");
+    assert_eq!(result.status.success(), true);
+    assert_eq!(stats(&copy_dir)?["release"], json!("21"));
+    assert!(stats(&copy_dir)?["created"].is_u64());
+    assert_eq!(stats(&db_dir)?["release"], json!(null));
+
+    let result = test_bin::get_test_bin("kv")
+        .args(&["compact", "--compression", "zstd", "--release", "22"])
+        .arg(&copy_dir)
+        .arg("first")
+        .output()?;
+    assert_eq!(String::from_utf8_lossy(&result.stderr), "");
+    assert_eq!(result.status.success(), true);
+    let compacted = stats(&copy_dir)?;
+    assert_eq!(compacted["release"], json!("22"));
+    assert_eq!(compacted["families"]["first"]["compression"], json!("zstd"));
+    assert_eq!(compacted["families"]["second"]["compression"], json!(null));
+
+    let id_file = temp_file_with(vec!["a2"])?;
+    let query = lookup(id_file.path(), &copy_dir, &PathBuf::from("-"))?;
+    assert_eq!(query.jsonl()?.len(), 1);
+
+    let result =
+        test_bin::get_test_bin("kv").arg("compact").arg(&copy_dir).arg("missing").output()?;
+    assert_eq!(result.status.success(), false);
+
+    Ok(())
+}
+
+#[test]
+fn lookup_output_formats_test() -> Result<(), Box<dyn Error>> {
+    let db_dir = dump_index()?;
+    let id_file = temp_file_with(vec!["a2", "b1"])?;
+    let lookup_as = |args: &[&str], output: &Path| {
+        test_bin::get_test_bin("kv")
+            .arg("lookup")
+            .args(args)
+            .arg(&db_dir)
+            .arg(id_file.path())
+            .arg(output)
+            .output()
+    };
+
+    let query = lookup_as(&["--format", "csv"], Path::new("-"))?;
+    assert_eq!(String::from_utf8_lossy(&query.stderr), "");
+    assert_eq!(
+        String::from_utf8_lossy(&query.stdout),
+        "id,first.id,first.value,second.id,second.other\na2,a2,2,a2,4\nb1,b1,3,,\n"
+    );
+
+    let query = lookup_as(&["--format", "tsv", "--fields", "first=value"], Path::new("-"))?;
+    assert_eq!(
+        String::from_utf8_lossy(&query.stdout),
+        "id\tfirst.value\tsecond.id\tsecond.other\na2\t2\ta2\t4\nb1\t3\t\t\n"
+    );
+
+    #[cfg(feature = "arrow")]
+    {
+        let dir = tempdir()?;
+        let arrow_file = dir.path().join("found.arrow");
+        let query = lookup_as(&["--format", "arrow"], &arrow_file)?;
+        assert_eq!(String::from_utf8_lossy(&query.stderr), "");
+        let reader =
+            arrow::ipc::reader::FileReader::try_new(std::fs::File::open(&arrow_file)?, None)?;
+        let schema = reader.schema();
+        let mut columns: Vec<&str> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+        columns.sort();
+        assert_eq!(columns, vec!["first", "id", "second"]);
+        let rows: usize = reader.map(|batch| batch.map(|b| b.num_rows())).sum::<Result<_, _>>()?;
+        assert_eq!(rows, 2);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn lookup_output_formats_use_all_blocks_test() -> Result<(), Box<dyn Error>> {
+    let db_dir = dump_index()?;
+    let id_file = temp_file_with(vec!["b1", "a2"])?;
+    let lookup_as = |format: &str, output: &Path| {
+        test_bin::get_test_bin("kv")
+            .args(&["lookup", "--threads", "1", "--batch-size", "1", "--format", format])
+            .arg(&db_dir)
+            .arg(id_file.path())
+            .arg(output)
+            .output()
+    };
+
+    let query = lookup_as("csv", Path::new("-"))?;
+    assert_eq!(String::from_utf8_lossy(&query.stderr), "");
+    assert_eq!(
+        String::from_utf8_lossy(&query.stdout),
+        "id,first.id,first.value,second.id,second.other\nb1,b1,3,,\na2,a2,2,a2,4\n"
+    );
+
+    #[cfg(feature = "arrow")]
+    {
+        let dir = tempdir()?;
+        let arrow_file = dir.path().join("found.arrow");
+        let query = lookup_as("arrow", &arrow_file)?;
+        assert_eq!(String::from_utf8_lossy(&query.stderr), "");
+        let reader =
+            arrow::ipc::reader::FileReader::try_new(std::fs::File::open(&arrow_file)?, None)?;
+        let second = reader.schema().field_with_name("second")?.data_type().to_string();
+        assert!(second.contains("other"), "{}", second);
+        let rows: usize = reader.map(|batch| batch.map(|b| b.num_rows())).sum::<Result<_, _>>()?;
+        assert_eq!(rows, 2);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn lookup_delimited_writes_every_combination_of_values_test() -> Result<(), Box<dyn Error>> {
+    let first = temp_file_with(vec![
+        r#"{"id": "a", "value": 1}"#,
+        r#"{"id": "a", "value": 2}"#,
+        r#"{"id": "b", "value": 3}"#,
+    ])?;
+    let second = temp_file_with(vec![
+        r#"{"id": "a", "other": "x"}"#,
+        r#"{"id": "a", "other": "y"}"#,
+        r#"{"id": "a", "other": "z"}"#,
+    ])?;
+    let db_dir = temp_index_dir()?;
+    assert_eq!(index("first", first.path(), &db_dir)?.status.success(), true);
+    assert_eq!(index("second", second.path(), &db_dir)?.status.success(), true);
+
+    let id_file = temp_file_with(vec!["a", "b"])?;
+    let query = test_bin::get_test_bin("kv")
+        .args(&["lookup", "--format", "csv", "--fields", "first=value", "--fields", "second=other"])
+        .arg(&db_dir)
+        .arg(id_file.path())
+        .arg("-")
+        .output()?;
+    assert_eq!(String::from_utf8_lossy(&query.stderr), "");
+    assert_eq!(
+        String::from_utf8_lossy(&query.stdout),
+        concat!(
+            "id,first.value,second.other\n",
+            "a,1,x\na,1,y\na,1,z\n",
+            "a,2,x\na,2,y\na,2,z\n",
+            "b,3,\n",
+        )
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn lookup_arrow_infers_schema_from_all_rows_test() -> Result<(), Box<dyn Error>> {
+    let data = temp_file_with(vec![
+        r#"{"id": "a", "value": 1}"#,
+        r#"{"id": "b", "value": 1.5, "note": "late"}"#,
+        r#"{"id": "c", "value": 2}"#,
+    ])?;
+    let db_dir = temp_index_dir()?;
+    assert_eq!(index("first", data.path(), &db_dir)?.status.success(), true);
+
+    let dir = tempdir()?;
+    let arrow_file = dir.path().join("found.arrow");
+    let id_file = temp_file_with(vec!["a", "b", "c"])?;
+    let query = test_bin::get_test_bin("kv")
+        .args(&["lookup", "--batch-size", "1", "--format", "arrow"])
+        .arg(&db_dir)
+        .arg(id_file.path())
+        .arg(&arrow_file)
+        .output()?;
+    assert_eq!(String::from_utf8_lossy(&query.stderr), "");
+    let reader = arrow::ipc::reader::FileReader::try_new(std::fs::File::open(&arrow_file)?, None)?;
+    let first = reader.schema().field_with_name("first")?.data_type().to_string();
+    assert!(first.contains("note"), "{}", first);
+    assert!(first.contains("Float64"), "{}", first);
+    let rows: usize = reader.map(|batch| batch.map(|b| b.num_rows())).sum::<Result<_, _>>()?;
+    assert_eq!(rows, 3);
+
+    Ok(())
+}
+
+#[test]
+fn index_with_key_validation_test() -> Result<(), Box<dyn Error>> {
+    let data_file = temp_file_with(vec![
+        r#"{"id": "urs0000000001_9606 ", "value": 1}"#,
+        r#"{"id": "URS0000000001", "value": 2}"#,
+        r#"{"id": "URS0000000001_9606", "value": 3}"#,
+    ])?;
+    let dir = tempdir()?;
+    let quarantine = dir.path().join("bad.json");
+    let db_dir = temp_index_dir()?;
+    let result = test_bin::get_test_bin("kv")
+        .args(&["index", "--validate-keys", "urs-taxid", "--quarantine"])
+        .arg(&quarantine)
+        .arg("hits")
+        .arg(data_file.path())
+        .arg(&db_dir)
+        .output()?;
+    assert_eq!(result.status.success(), true);
+    assert_eq!(
+        std::fs::read_to_string(&quarantine)?,
+        "{\"id\": \"URS0000000001\", \"value\": 2}\n"
+    );
+
+    let id_file = temp_file_with(vec!["URS0000000001_9606"])?;
+    let query = lookup(id_file.path(), &db_dir, &PathBuf::from("-"))?;
+    assert_eq!(query.jsonl()?[0]["hits"].as_array().unwrap().len(), 2);
+
+    Ok(())
+}
@@ -1,7 +1,6 @@
 use std::{
     collections::HashMap,
-    fs::File,
-    io::Write,
+    io,
     path::{
         Path,
         PathBuf,
@@ -77,9 +76,9 @@ fn fetch_files(raw: &Path) -> anyhow::Result<Vec<PathBuf>> {
 fn write_lookup(
     filename: &Path,
     mapping: &mut Mapping,
-    writer: &mut csv::Writer<Box<dyn Write>>,
+    writer: &mut csv::Writer<rnc_utils::Writer>,
 ) -> anyhow::Result<()> {
-    let mut iter = XmlIterator::from_file(&filename)?;
+    let mut iter = XmlIterator::from_file(filename)?;
     while let Some(reference) = iter.next()? {
         for pub_id in reference.external_ids() {
             log::trace!("Looking up match references using {:?}", &pub_id);
@@ -106,6 +105,14 @@ fn write_lookup(
     Ok(())
 }
 
+/// Flush the csv writer and finish the compressed output under it.
+fn finish(writer: csv::Writer<rnc_utils::Writer>) -> anyhow::Result<()> {
+    let output =
+        writer.into_inner().map_err(|err| io::Error::new(err.error().kind(), err.to_string()))?;
+    output.finish()?;
+    Ok(())
+}
+
 pub fn write_references(
     xml_directory: &Path,
     raw: &Path,
@@ -131,15 +138,17 @@ pub fn write_references(
         write_lookup(&xml_file, &mut mapping, &mut writer)?;
         if mapping.is_empty() {
             log::debug!("Ending early, nothing left to map");
+            finish(writer)?;
             return Ok(());
         }
     }
+    finish(writer)?;
 
     match missing_to {
         None => (),
         Some(path) => {
             log::info!("Writing {} missing data to {:?}", mapping.len(), &path);
-            let missing = File::create(&path)?;
+            let missing = rnc_utils::writer(&path)?;
             let mut missing = csv::Writer::from_writer(missing);
             for (key, records) in &mapping {
                 log::warn!("Did not find publication information for {:?}", &key);
@@ -147,6 +156,7 @@ pub fn write_references(
                     missing.serialize(&record)?;
                 }
             }
+            finish(missing)?;
         },
     }
 
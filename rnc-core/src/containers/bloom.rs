@@ -0,0 +1,343 @@
+use std::{
+    cell::Cell,
+    f64::consts::LN_2,
+    io,
+    io::{
+        Read,
+        Write,
+    },
+};
+
+use thiserror::Error;
+
+use crate::{
+    containers::{
+        urs::UrsStore,
+        urs_taxid::UrsTaxidStore,
+    },
+    urs::Urs,
+    urs_taxid::UrsTaxid,
+};
+
+/// The first bytes of every serialized filter.
+const MAGIC: &[u8; 8] = b"RNCBLOOM";
+
+/// The most hashes a filter may use. Even a false positive rate of 1e-18 needs fewer, so more
+/// only means a corrupt filter.
+const MAX_HASHES: u64 = 64;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("IO Error")]
+    Io(#[from] io::Error),
+
+    #[error("Not a serialized bloom filter")]
+    MissingMagic,
+
+    #[error("False positive rate must be between 0 and 1, not {0}")]
+    InvalidRate(f64),
+
+    #[error("Bloom filter has an invalid size of {0} bits and {1} hashes")]
+    InvalidSize(u64, u64),
+}
+
+/// A Bloom filter of URS. This never misses a URS which was added, but may claim to contain
+/// URS which were not, at about the false positive rate it was created with. It uses a fixed
+/// amount of memory, about 10 bits per URS at a 1% false positive rate, so it is useful to
+/// cheaply filter large streams before any exact check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BloomStore {
+    bits: Vec<u64>,
+    size: u64,
+    hashes: u64,
+}
+
+impl UrsStore for BloomStore {
+    fn add(&mut self, urs: &Urs) {
+        for index in self.indexes(urs.into()) {
+            self.bits[(index / 64) as usize] |= 1 << (index % 64);
+        }
+    }
+
+    fn contains(&self, urs: &Urs) -> bool {
+        self.indexes(urs.into()).all(|index| {
+            let mask = 1 << (index % 64);
+            self.bits[(index / 64) as usize] & mask != 0
+        })
+    }
+}
+
+impl BloomStore {
+    /// Create an empty filter sized to hold the expected number of URS with the given false
+    /// positive rate. Adding more URS than expected increases the false positive rate.
+    pub fn new(expected: usize, false_positive_rate: f64) -> Result<Self, Error> {
+        if !(false_positive_rate > 0.0 && false_positive_rate < 1.0) {
+            return Err(Error::InvalidRate(false_positive_rate));
+        }
+
+        let expected = expected.max(1) as f64;
+        let size = (-expected * false_positive_rate.ln() / (LN_2 * LN_2)).ceil().max(64.0) as u64;
+        let hashes = ((size as f64 / expected) * LN_2).round().max(1.0).min(MAX_HASHES as f64);
+        Ok(Self {
+            bits: vec![0; size.div_ceil(64) as usize],
+            size,
+            hashes: hashes as u64,
+        })
+    }
+
+    /// The number of bits in the filter.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// The number of hashes used for each URS.
+    pub fn hashes(&self) -> u64 {
+        self.hashes
+    }
+
+    /// The bits to use for a URS, computed with double hashing of the URS id.
+    fn indexes(&self, id: u64) -> impl Iterator<Item = u64> {
+        let first = mix(id);
+        let second = mix(first ^ 0x9e37_79b9_7f4a_7c15) | 1;
+        let size = self.size;
+        (0..self.hashes).map(move |i| first.wrapping_add(i.wrapping_mul(second)) % size)
+    }
+
+    /// Write the filter, so it can be read back with `read_from` instead of being rebuilt.
+    pub fn write_to<W: Write>(&self, mut out: W) -> Result<(), Error> {
+        out.write_all(MAGIC)?;
+        out.write_all(&self.size.to_le_bytes())?;
+        out.write_all(&self.hashes.to_le_bytes())?;
+        for word in &self.bits {
+            out.write_all(&word.to_le_bytes())?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Read a filter written by `write_to`. The size in the header is not trusted, the bits are
+    /// only stored as they are read so a corrupt header cannot cause a huge allocation.
+    pub fn read_from<R: Read>(mut input: R) -> Result<Self, Error> {
+        let mut magic = [0; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Error::MissingMagic);
+        }
+
+        let size = read_u64(&mut input)?;
+        let hashes = read_u64(&mut input)?;
+        if size == 0 || size > u64::MAX - 63 || hashes == 0 || hashes > MAX_HASHES {
+            return Err(Error::InvalidSize(size, hashes));
+        }
+
+        let mut bits = Vec::new();
+        for _ in 0..size.div_ceil(64) {
+            bits.push(read_u64(&mut input)?);
+        }
+        Ok(Self {
+            bits,
+            size,
+            hashes,
+        })
+    }
+}
+
+/// The finalizer of splitmix64, which spreads the bits of similar ids across the whole word.
+fn mix(value: u64) -> u64 {
+    let mut value = value;
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    value ^ (value >> 31)
+}
+
+fn read_u64<R: Read>(input: &mut R) -> Result<u64, Error> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// A store which uses a probabilistic filter to quickly reject URS, and confirms anything the
+/// filter accepts against an exact store, of URS or of URS_taxids. This gives exact answers,
+/// while keeping count of how often the filter was wrong so its false positive rate can be
+/// checked.
+pub struct Verified<F: UrsStore, E> {
+    filter: F,
+    exact: E,
+    positives: Cell<u64>,
+    false_positives: Cell<u64>,
+}
+
+impl<F: UrsStore, E: UrsStore> UrsStore for Verified<F, E> {
+    fn add(&mut self, urs: &Urs) {
+        self.filter.add(urs);
+        self.exact.add(urs);
+    }
+
+    fn contains(&self, urs: &Urs) -> bool {
+        self.verify(urs, || self.exact.contains(urs))
+    }
+}
+
+/// A filter of URS can also screen an exact store of URS_taxids, by checking the URS of each
+/// URS_taxid.
+impl<F: UrsStore, E: UrsTaxidStore> UrsTaxidStore for Verified<F, E> {
+    fn urs_taxids(&self, urs: &Urs) -> Vec<UrsTaxid> {
+        let mut found = Vec::new();
+        self.verify(urs, || {
+            found = self.exact.urs_taxids(urs);
+            !found.is_empty()
+        });
+        found
+    }
+
+    fn contains(&self, urs_taxid: &UrsTaxid) -> bool {
+        self.verify(&urs_taxid.into(), || self.exact.contains(urs_taxid))
+    }
+
+    fn contains_urs(&self, urs: &Urs) -> bool {
+        self.verify(urs, || self.exact.contains_urs(urs))
+    }
+}
+
+impl<F: UrsStore, E> Verified<F, E> {
+    pub fn new(filter: F, exact: E) -> Self {
+        Self {
+            filter,
+            exact,
+            positives: Cell::new(0),
+            false_positives: Cell::new(0),
+        }
+    }
+
+    /// The number of lookups the filter accepted.
+    pub fn positives(&self) -> u64 {
+        self.positives.get()
+    }
+
+    /// The number of lookups the filter accepted but the exact store rejected.
+    pub fn false_positives(&self) -> u64 {
+        self.false_positives.get()
+    }
+
+    pub fn into_inner(self) -> (F, E) {
+        (self.filter, self.exact)
+    }
+
+    /// Check the URS with the filter and, only if it is accepted, with the exact check.
+    fn verify(&self, urs: &Urs, exact: impl FnOnce() -> bool) -> bool {
+        if !self.filter.contains(urs) {
+            return false;
+        }
+
+        self.positives.set(self.positives.get() + 1);
+        let found = exact();
+        if !found {
+            self.false_positives.set(self.false_positives.get() + 1);
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    use crate::containers::{
+        urs::BasicStore,
+        urs_taxid::UrsTaxidMapping,
+    };
+
+    fn filled(count: u64, rate: f64) -> BloomStore {
+        let mut filter = BloomStore::new(count as usize, rate).unwrap();
+        for id in 0..count {
+            filter.add(&Urs::from(id * 2));
+        }
+        filter
+    }
+
+    #[test]
+    fn never_misses_added_urs() {
+        let filter = filled(10_000, 0.01);
+        for id in 0..10_000 {
+            assert!(filter.contains(&Urs::from(id * 2)));
+        }
+    }
+
+    #[test]
+    fn has_about_the_requested_false_positive_rate() {
+        let filter = filled(10_000, 0.01);
+        let found = (0..10_000).filter(|id| filter.contains(&Urs::from(id * 2 + 1))).count();
+        assert!(found < 300, "Found {} false positives", found);
+    }
+
+    #[test]
+    fn rejects_invalid_rates() {
+        assert!(matches!(BloomStore::new(10, 0.0), Err(super::Error::InvalidRate(_))));
+        assert!(matches!(BloomStore::new(10, 1.0), Err(super::Error::InvalidRate(_))));
+    }
+
+    #[test]
+    fn can_write_and_read_filters() -> Result<(), Box<dyn Error>> {
+        let filter = filled(1_000, 0.01);
+        let mut buf = Vec::new();
+        filter.write_to(&mut buf)?;
+        assert_eq!(BloomStore::read_from(&buf[..])?, filter);
+        let other = BloomStore::read_from(&b"URS0000762A36"[..]);
+        assert!(matches!(other, Err(super::Error::MissingMagic)));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_corrupt_headers_without_allocating() {
+        let header = |size: u64, hashes: u64| {
+            let mut buf = MAGIC.to_vec();
+            buf.extend_from_slice(&size.to_le_bytes());
+            buf.extend_from_slice(&hashes.to_le_bytes());
+            buf
+        };
+
+        let huge = BloomStore::read_from(&header(u64::MAX - 63, 7)[..]);
+        assert!(matches!(huge, Err(super::Error::Io(_))));
+        let overflow = BloomStore::read_from(&header(u64::MAX, 7)[..]);
+        assert!(matches!(overflow, Err(super::Error::InvalidSize(_, 7))));
+        let hashes = BloomStore::read_from(&header(64, MAX_HASHES + 1)[..]);
+        assert!(matches!(hashes, Err(super::Error::InvalidSize(64, _))));
+    }
+
+    #[test]
+    fn verified_stores_are_exact() {
+        let filter = filled(100, 0.5);
+        let mut exact = BasicStore::default();
+        for id in 0..100 {
+            exact.add(&Urs::from(id * 2));
+        }
+
+        let verified = Verified::new(filter, exact);
+        for id in 0..1_000 {
+            assert_eq!(verified.contains(&Urs::from(id)), id % 2 == 0 && id < 200);
+        }
+        assert!(verified.positives() >= 100);
+        assert!(verified.false_positives() > 0);
+        assert_eq!(verified.positives() - verified.false_positives(), 100);
+    }
+
+    #[test]
+    fn can_verify_urs_taxid_stores() {
+        let mut exact = UrsTaxidMapping::default();
+        let mut filter = BloomStore::new(100, 0.5).unwrap();
+        for id in 0..100 {
+            exact.add(&UrsTaxid::new(id * 2, 9606));
+            filter.add(&Urs::from(id * 2));
+        }
+
+        let verified = Verified::new(filter, exact);
+        for id in 0..1_000 {
+            let expected = id % 2 == 0 && id < 200;
+            assert_eq!(verified.contains(&UrsTaxid::new(id, 9606)), expected);
+            assert_eq!(verified.contains(&UrsTaxid::new(id, 562)), false);
+            assert_eq!(verified.urs_taxids(&Urs::from(id)).len(), expected as usize);
+        }
+        assert!(verified.false_positives() > 0);
+    }
+}
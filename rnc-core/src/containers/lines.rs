@@ -0,0 +1,125 @@
+use std::{
+    error,
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+    str::FromStr,
+};
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Could not read {}", .0.display())]
+    Io(PathBuf, #[source] io::Error),
+
+    #[error("Could not parse line {line} of {}, '{raw}'", .path.display())]
+    Parse {
+        path: PathBuf,
+        line: usize,
+        raw: String,
+        #[source]
+        source: Box<dyn error::Error + Send + Sync>,
+    },
+}
+
+/// Parse each line of a file, '-' meaning stdin, as an id and give it to the callback. This
+/// streams the file so the callback decides what to keep, and stops at the first line which
+/// cannot be parsed, reporting its line number. Blank lines are skipped.
+pub fn each_id<T, F>(path: &Path, callback: F) -> Result<(), Error>
+where
+    T: FromStr,
+    T::Err: error::Error + Send + Sync + 'static,
+    F: FnMut(T),
+{
+    each_parsed(path, |raw| raw.parse(), callback)
+}
+
+/// Parse each line of a file like `each_id`, but with the given parser instead of `FromStr`,
+/// like `UrsTaxid::parse_any_case`.
+pub fn each_parsed<T, E, P, F>(path: &Path, parse: P, mut callback: F) -> Result<(), Error>
+where
+    E: error::Error + Send + Sync + 'static,
+    P: Fn(&str) -> Result<T, E>,
+    F: FnMut(T),
+{
+    let io_error = |err| Error::Io(path.to_path_buf(), err);
+    let mut reader = rnc_utils::buf_reader(path).map_err(io_error)?;
+    let mut buf = String::new();
+    let mut line = 0;
+    loop {
+        buf.clear();
+        line += 1;
+        if reader.read_line(&mut buf).map_err(io_error)? == 0 {
+            break;
+        }
+
+        let raw = buf.trim_end();
+        if raw.is_empty() {
+            continue;
+        }
+        let id = parse(raw).map_err(|err| Error::Parse {
+            path: path.to_path_buf(),
+            line,
+            raw: raw.to_string(),
+            source: Box::new(err),
+        })?;
+        callback(id);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    use crate::{
+        urs::Urs,
+        urs_taxid::UrsTaxid,
+    };
+
+    use rnc_test_utils::temp_file_with;
+
+    #[test]
+    fn can_parse_each_id() -> Result<(), Box<dyn Error>> {
+        let file = temp_file_with(vec!["URS0000762A36_9606", "", "URS0000000001_562"])?;
+        let mut ids: Vec<UrsTaxid> = Vec::new();
+        each_id(file.path(), |id| ids.push(id))?;
+        assert_eq!(ids, vec![
+            UrsTaxid::new(0x762A36, 9606),
+            UrsTaxid::new(1, 562),
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn can_parse_with_a_given_parser() -> Result<(), Box<dyn Error>> {
+        let file = temp_file_with(vec!["urs0000762a36_9606"])?;
+        let mut ids = Vec::new();
+        each_parsed(file.path(), UrsTaxid::parse_any_case, |id| ids.push(id))?;
+        assert_eq!(ids, vec![UrsTaxid::new(0x762A36, 9606)]);
+        Ok(())
+    }
+
+    #[test]
+    fn reports_the_line_which_failed() -> Result<(), Box<dyn Error>> {
+        let file = temp_file_with(vec!["URS0000762A36", "URS0000000001", "bob"])?;
+        let mut ids: Vec<Urs> = Vec::new();
+        match each_id(file.path(), |id| ids.push(id)) {
+            Err(super::Error::Parse {
+                line,
+                raw,
+                ..
+            }) => {
+                assert_eq!(line, 3);
+                assert_eq!(raw, "bob");
+            },
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(ids.len(), 2);
+        Ok(())
+    }
+}
@@ -1,2 +1,5 @@
+pub mod bloom;
+pub mod lines;
+pub mod table;
 pub mod urs_taxid;
 pub mod urs;
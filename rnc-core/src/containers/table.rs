@@ -0,0 +1,473 @@
+use std::{
+    convert::TryInto,
+    fs::File,
+    io,
+    io::{
+        Read,
+        Write,
+    },
+    iter::once,
+    path::Path,
+};
+
+use memmap2::Mmap;
+
+use thiserror::Error;
+
+use crate::{
+    urs::Urs,
+    urs_taxid::UrsTaxid,
+};
+
+/// The first bytes of every table file.
+const MAGIC: &[u8; 8] = b"RNCTABLE";
+
+/// The version of the layout written after the magic. This must change whenever the layout
+/// does, so old tables are rejected instead of misread.
+const VERSION: u64 = 2;
+
+/// The size of the header, the magic, the version, the kind and the number of entries.
+const HEADER_SIZE: usize = 32;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("IO Error")]
+    Io(#[from] io::Error),
+
+    #[error("Not a URS table")]
+    MissingMagic,
+
+    #[error("Unsupported table version {0}, only version {} is supported", VERSION)]
+    UnsupportedVersion(u64),
+
+    #[error("Unknown kind of table {0}")]
+    UnknownKind(u64),
+
+    #[error("Expected a table of {0:?} but found one of {1:?}")]
+    WrongKind(Kind, Kind),
+
+    #[error("Table should be {0} bytes but is {1} bytes")]
+    WrongSize(usize, usize),
+
+    #[error("Table entry {0} is not after the entry before it")]
+    Unsorted(usize),
+}
+
+/// What the entries of a table are. A table of URS stores one `u64` per entry, while a table
+/// of URS_taxids stores a (urs, taxid) pair of `u64`s per entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Urs,
+    UrsTaxid,
+}
+
+impl Kind {
+    fn code(&self) -> u64 {
+        match self {
+            Kind::Urs => 1,
+            Kind::UrsTaxid => 2,
+        }
+    }
+
+    fn from_code(code: u64) -> Result<Self, Error> {
+        match code {
+            1 => Ok(Kind::Urs),
+            2 => Ok(Kind::UrsTaxid),
+            _ => Err(Error::UnknownKind(code)),
+        }
+    }
+
+    /// The number of bytes in each entry.
+    fn entry_size(&self) -> usize {
+        match self {
+            Kind::Urs => 8,
+            Kind::UrsTaxid => 16,
+        }
+    }
+}
+
+/// Check if the file at the given path starts like a table, so tools can accept either a table
+/// or a text file of ids. Stdin, '-', is never a table as it cannot be mapped.
+pub fn is_table(path: &Path) -> io::Result<bool> {
+    if path == Path::new("-") {
+        return Ok(false);
+    }
+    let mut magic = [0; 8];
+    let mut file = File::open(path)?;
+    match file.read_exact(&mut magic) {
+        Ok(_) => Ok(&magic == MAGIC),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Write a table of the given URS. These do not need to be sorted or unique. This returns the
+/// number of entries written. The output must be a plain file for the table to be usable, as
+/// tables are memory mapped and not read through a decompressor.
+pub fn write_urs<W: Write, I: IntoIterator<Item = Urs>>(out: W, urs: I) -> Result<usize, Error> {
+    let mut ids: Vec<u64> = urs.into_iter().map(|u| u.into()).collect();
+    ids.sort_unstable();
+    ids.dedup();
+    write_table(out, Kind::Urs, ids.len(), ids.into_iter())
+}
+
+/// Write a table of the given URS_taxids. These do not need to be sorted or unique. This
+/// returns the number of entries written. The output must be a plain file for the table to be
+/// usable, as tables are memory mapped and not read through a decompressor.
+pub fn write_urs_taxids<W, I>(out: W, urs_taxids: I) -> Result<usize, Error>
+where
+    W: Write,
+    I: IntoIterator<Item = UrsTaxid>,
+{
+    let mut pairs: Vec<(u64, u64)> =
+        urs_taxids.into_iter().map(|ut| (ut.urs(), ut.taxid())).collect();
+    pairs.sort_unstable();
+    pairs.dedup();
+    let values = pairs.iter().flat_map(|(urs, taxid)| once(*urs).chain(once(*taxid)));
+    write_table(out, Kind::UrsTaxid, pairs.len(), values)
+}
+
+fn write_table<W, V>(mut out: W, kind: Kind, count: usize, values: V) -> Result<usize, Error>
+where
+    W: Write,
+    V: Iterator<Item = u64>,
+{
+    out.write_all(MAGIC)?;
+    out.write_all(&VERSION.to_le_bytes())?;
+    out.write_all(&kind.code().to_le_bytes())?;
+    out.write_all(&(count as u64).to_le_bytes())?;
+    for value in values {
+        out.write_all(&value.to_le_bytes())?;
+    }
+    out.flush()?;
+    Ok(count)
+}
+
+fn read_u64(bytes: &[u8], start: usize) -> u64 {
+    u64::from_le_bytes(bytes[start..start + 8].try_into().unwrap())
+}
+
+/// The memory mapped entries of a table file, which are sorted so they can be searched without
+/// loading the file. Opening only checks the header and the size, so it does not touch the
+/// entries, the order of the entries is only checked by `verify`.
+struct Table {
+    map: Option<Mmap>,
+    kind: Kind,
+    len: usize,
+}
+
+impl Table {
+    fn open(path: &Path) -> Result<Self, Error> {
+        let mut file = File::open(path)?;
+        let mut header = [0; HEADER_SIZE];
+        match file.read_exact(&mut header) {
+            Ok(_) => (),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                return Err(Error::MissingMagic)
+            },
+            Err(err) => return Err(err.into()),
+        }
+        if &header[0..8] != MAGIC {
+            return Err(Error::MissingMagic);
+        }
+        let version = read_u64(&header, 8);
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+
+        let kind = Kind::from_code(read_u64(&header, 16))?;
+        let len = read_u64(&header, 24) as usize;
+        let expected = len
+            .checked_mul(kind.entry_size())
+            .and_then(|size| size.checked_add(HEADER_SIZE))
+            .unwrap_or(usize::MAX);
+        let size = file.metadata()?.len() as usize;
+        if size != expected {
+            return Err(Error::WrongSize(expected, size));
+        }
+
+        // A table without entries is only a header, there is nothing worth mapping.
+        if len == 0 {
+            return Ok(Self {
+                map: None,
+                kind,
+                len,
+            });
+        }
+
+        // Tables are written once and then only read, so the file is not expected to change
+        // while it is mapped.
+        let map = unsafe { Mmap::map(&file)? };
+        if map.len() != expected {
+            return Err(Error::WrongSize(expected, map.len()));
+        }
+        Ok(Self {
+            map: Some(map),
+            kind,
+            len,
+        })
+    }
+
+    /// Check that every entry is strictly after the one before it, which searching relies on.
+    /// This reads the whole table.
+    fn verify(&self) -> Result<(), Error> {
+        match (1..self.len).find(|&i| self.entry(i - 1) >= self.entry(i)) {
+            Some(index) => Err(Error::Unsorted(index)),
+            None => Ok(()),
+        }
+    }
+
+    /// The entry at the index as a (urs, taxid) pair, where the taxid is always 0 for a table
+    /// of URS.
+    fn entry(&self, index: usize) -> (u64, u64) {
+        let map = self.map.as_ref().expect("Only tables with entries are mapped");
+        let start = HEADER_SIZE + index * self.kind.entry_size();
+        match self.kind {
+            Kind::Urs => (read_u64(map, start), 0),
+            Kind::UrsTaxid => (read_u64(map, start), read_u64(map, start + 8)),
+        }
+    }
+
+    /// The index of the first entry which is not less than the given pair.
+    fn lower_bound(&self, target: (u64, u64)) -> usize {
+        let mut low = 0;
+        let mut high = self.len;
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if self.entry(middle) < target {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        low
+    }
+
+    fn contains_urs(&self, urs: u64) -> bool {
+        let index = self.lower_bound((urs, 0));
+        index < self.len && self.entry(index).0 == urs
+    }
+}
+
+/// A set of URS backed by a table file, the mapped replacement for `BasicStore`. Either kind of
+/// table can be used, for a table of URS_taxids this contains the URS of each URS_taxid.
+pub struct UrsTable {
+    table: Table,
+}
+
+impl UrsTable {
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        Ok(Self {
+            table: Table::open(path)?,
+        })
+    }
+
+    pub fn kind(&self) -> Kind {
+        self.table.kind
+    }
+
+    /// Check the table is sorted, see `UrsTaxidTable::verify`.
+    pub fn verify(&self) -> Result<(), Error> {
+        self.table.verify()
+    }
+
+    pub fn contains(&self, urs: &Urs) -> bool {
+        self.table.contains_urs(urs.into())
+    }
+
+    pub fn contains_parent_urs(&self, urs_taxid: &UrsTaxid) -> bool {
+        self.table.contains_urs(urs_taxid.urs())
+    }
+}
+
+/// A set of URS_taxids backed by a table of URS_taxids, the mapped replacement for
+/// `UrsTaxidMapping`, which can also find all URS_taxids of a URS.
+pub struct UrsTaxidTable {
+    table: Table,
+}
+
+impl UrsTaxidTable {
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let table = Table::open(path)?;
+        if table.kind != Kind::UrsTaxid {
+            return Err(Error::WrongKind(Kind::UrsTaxid, table.kind));
+        }
+        Ok(Self {
+            table,
+        })
+    }
+
+    /// Check the table is sorted. Opening a table does not, as that would read every page of
+    /// it, so this is meant for checking a table once after it is built or copied.
+    pub fn verify(&self) -> Result<(), Error> {
+        self.table.verify()
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.len == 0
+    }
+
+    pub fn contains(&self, urs_taxid: &UrsTaxid) -> bool {
+        let target = (urs_taxid.urs(), urs_taxid.taxid());
+        let index = self.table.lower_bound(target);
+        index < self.table.len && self.table.entry(index) == target
+    }
+
+    pub fn contains_urs(&self, urs: &Urs) -> bool {
+        self.table.contains_urs(urs.into())
+    }
+
+    pub fn urs_taxids(&self, urs: &Urs) -> Vec<UrsTaxid> {
+        let id: u64 = urs.into();
+        let mut found = Vec::new();
+        for index in self.table.lower_bound((id, 0))..self.table.len {
+            let (found_urs, taxid) = self.table.entry(index);
+            if found_urs != id {
+                break;
+            }
+            found.push(UrsTaxid::new(id, taxid));
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    use tempfile::NamedTempFile;
+
+    fn urs(raw: &str) -> Urs {
+        raw.parse().unwrap()
+    }
+
+    fn urs_taxid(raw: &str) -> UrsTaxid {
+        raw.parse().unwrap()
+    }
+
+    fn raw_table(version: u64, entries: &[(u64, u64)]) -> Result<NamedTempFile, Box<dyn Error>> {
+        let file = NamedTempFile::new()?;
+        let mut out = file.as_file();
+        out.write_all(MAGIC)?;
+        out.write_all(&version.to_le_bytes())?;
+        out.write_all(&Kind::UrsTaxid.code().to_le_bytes())?;
+        out.write_all(&(entries.len() as u64).to_le_bytes())?;
+        for (urs, taxid) in entries {
+            out.write_all(&urs.to_le_bytes())?;
+            out.write_all(&taxid.to_le_bytes())?;
+        }
+        Ok(file)
+    }
+
+    #[test]
+    fn can_write_and_search_urs_taxid_tables() -> Result<(), Box<dyn Error>> {
+        let file = NamedTempFile::new()?;
+        let ids = vec![
+            urs_taxid("URS0000762A36_9606"),
+            urs_taxid("URS0000762A36_562"),
+            urs_taxid("URS0000000001_9606"),
+            urs_taxid("URS0000762A36_9606"),
+        ];
+        assert_eq!(write_urs_taxids(file.as_file(), ids)?, 3);
+        assert_eq!(is_table(file.path())?, true);
+
+        let table = UrsTaxidTable::open(file.path())?;
+        table.verify()?;
+        assert_eq!(table.len(), 3);
+        assert_eq!(table.contains(&urs_taxid("URS0000762A36_9606")), true);
+        assert_eq!(table.contains(&urs_taxid("URS0000762A36_10090")), false);
+        assert_eq!(table.contains_urs(&urs("URS0000000001")), true);
+        assert_eq!(table.contains_urs(&urs("URS0000000002")), false);
+        assert_eq!(table.urs_taxids(&urs("URS0000762A36")), vec![
+            urs_taxid("URS0000762A36_562"),
+            urs_taxid("URS0000762A36_9606"),
+        ]);
+        assert_eq!(table.urs_taxids(&urs("URS0000000002")), vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn can_write_and_search_urs_tables() -> Result<(), Box<dyn Error>> {
+        let file = NamedTempFile::new()?;
+        let ids = vec![urs("URS0000762A36"), urs("URS0000000001"), urs("URS0000762A36")];
+        assert_eq!(write_urs(file.as_file(), ids)?, 2);
+
+        let table = UrsTable::open(file.path())?;
+        table.verify()?;
+        assert_eq!(table.kind(), Kind::Urs);
+        assert_eq!(table.contains(&urs("URS0000762A36")), true);
+        assert_eq!(table.contains(&urs("URS0000000002")), false);
+        assert_eq!(table.contains_parent_urs(&urs_taxid("URS0000000001_9606")), true);
+        assert!(matches!(
+            UrsTaxidTable::open(file.path()),
+            Err(super::Error::WrongKind(Kind::UrsTaxid, Kind::Urs))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn can_use_urs_taxid_tables_as_urs_tables() -> Result<(), Box<dyn Error>> {
+        let file = NamedTempFile::new()?;
+        write_urs_taxids(file.as_file(), vec![urs_taxid("URS0000762A36_9606")])?;
+
+        let table = UrsTable::open(file.path())?;
+        assert_eq!(table.kind(), Kind::UrsTaxid);
+        assert_eq!(table.contains(&urs("URS0000762A36")), true);
+        assert_eq!(table.contains(&urs("URS0000000001")), false);
+        Ok(())
+    }
+
+    #[test]
+    fn can_open_tables_without_entries() -> Result<(), Box<dyn Error>> {
+        let file = NamedTempFile::new()?;
+        assert_eq!(write_urs_taxids(file.as_file(), vec![])?, 0);
+
+        let table = UrsTaxidTable::open(file.path())?;
+        table.verify()?;
+        assert_eq!(table.is_empty(), true);
+        assert_eq!(table.contains(&urs_taxid("URS0000762A36_9606")), false);
+        assert_eq!(table.urs_taxids(&urs("URS0000762A36")), vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_files_which_are_not_tables() -> Result<(), Box<dyn Error>> {
+        let file = NamedTempFile::new()?;
+        assert_eq!(is_table(file.path())?, false);
+        assert!(matches!(UrsTaxidTable::open(file.path()), Err(super::Error::MissingMagic)));
+
+        writeln!(file.as_file(), "URS0000762A36_9606")?;
+        assert_eq!(is_table(file.path())?, false);
+        assert!(matches!(UrsTaxidTable::open(file.path()), Err(super::Error::MissingMagic)));
+
+        let file = NamedTempFile::new()?;
+        write_urs_taxids(file.as_file(), vec![urs_taxid("URS0000762A36_9606")])?;
+        file.as_file().set_len(40)?;
+        assert!(matches!(UrsTaxidTable::open(file.path()), Err(super::Error::WrongSize(48, 40))));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_other_versions_and_unsorted_tables() -> Result<(), Box<dyn Error>> {
+        let file = raw_table(VERSION + 1, &[(1, 9606)])?;
+        assert!(matches!(
+            UrsTaxidTable::open(file.path()),
+            Err(super::Error::UnsupportedVersion(3))
+        ));
+
+        // Opening only checks the header, the order is checked by verify.
+        let file = raw_table(VERSION, &[(1, 9606), (2, 562), (1, 562)])?;
+        let table = UrsTaxidTable::open(file.path())?;
+        assert!(matches!(table.verify(), Err(super::Error::Unsorted(2))));
+
+        let file = raw_table(VERSION, &[(1, 9606), (1, 9606)])?;
+        let table = UrsTaxidTable::open(file.path())?;
+        assert!(matches!(table.verify(), Err(super::Error::Unsorted(1))));
+        Ok(())
+    }
+}
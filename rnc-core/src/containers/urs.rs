@@ -1,16 +1,9 @@
-use std::{
-    error::Error,
-    fs::File,
-    io::{
-        BufRead,
-        BufReader,
-    },
-    path::Path,
-};
+use std::path::Path;
 
 use fnv::FnvHashSet;
 
 use crate::{
+    containers::lines,
     urs::Urs,
     urs_taxid::UrsTaxid,
 };
@@ -28,6 +21,7 @@ pub trait UrsStore {
     }
 }
 
+#[derive(Default)]
 pub struct BasicStore {
     set: FnvHashSet<u64>,
 }
@@ -42,34 +36,11 @@ impl UrsStore for BasicStore {
     }
 }
 
-impl Default for BasicStore {
-    fn default() -> Self {
-        Self {
-            set: FnvHashSet::default(),
-        }
-    }
-}
-
 impl BasicStore {
-    pub fn from_urs_file(path: &Path) -> Result<Self, Box<dyn Error>> {
-        let file = File::open(path)?;
-        let mut reader = BufReader::new(file);
-
-        let mut set: FnvHashSet<u64> = FnvHashSet::default();
-        let mut buf = String::new();
-        loop {
-            match reader.read_line(&mut buf)? {
-                0 => break,
-                _ => {
-                    let urs: Urs = buf.trim_end().parse()?;
-                    set.insert(urs.into());
-                    buf.clear();
-                },
-            }
-        }
-
-        Ok(Self {
-            set,
-        })
+    /// Load the URS in a file, one per line. The URS may be in any case.
+    pub fn from_urs_file(path: &Path) -> Result<Self, lines::Error> {
+        let mut store = Self::default();
+        lines::each_parsed(path, Urs::parse_any_case, |urs| store.add(&urs))?;
+        Ok(store)
     }
 }
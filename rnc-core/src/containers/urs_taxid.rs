@@ -1,12 +1,4 @@
-use std::{
-    fs::File,
-    io,
-    io::{
-        BufRead,
-        BufReader,
-    },
-    path::Path,
-};
+use std::path::Path;
 
 use fnv::{
     FnvHashMap,
@@ -16,57 +8,71 @@ use fnv::{
 use thiserror::Error;
 
 use crate::{
+    containers::{
+        lines,
+        table,
+        table::UrsTaxidTable,
+    },
     urs::Urs,
-    urs_taxid,
     urs_taxid::UrsTaxid,
 };
 
 #[derive(Error, Debug)]
 pub enum Error {
-    #[error("IO Error")]
-    Io(#[from] io::Error),
+    #[error(transparent)]
+    Lines(#[from] lines::Error),
 
-    #[error("Incorrectly formatted URS_taxid")]
-    UrsTaxidError(#[from] urs_taxid::Error),
+    #[error(transparent)]
+    Table(#[from] table::Error),
 }
 
-pub struct UrsTaxidMapping {
-    mapping: FnvHashMap<u64, FnvHashSet<u64>>,
-}
+/// A read only collection of URS_taxids, which can be asked about a single URS_taxid or about
+/// every URS_taxid of a URS.
+pub trait UrsTaxidStore {
+    /// All URS_taxids of the URS, in no particular order.
+    fn urs_taxids(&self, urs: &Urs) -> Vec<UrsTaxid>;
 
-impl Default for UrsTaxidMapping {
-    fn default() -> Self {
-        Self {
-            mapping: FnvHashMap::default(),
-        }
+    fn contains(&self, urs_taxid: &UrsTaxid) -> bool {
+        self.urs_taxids(&urs_taxid.into()).contains(urs_taxid)
+    }
+
+    /// Check if there is any URS_taxid of the URS.
+    fn contains_urs(&self, urs: &Urs) -> bool {
+        !self.urs_taxids(urs).is_empty()
     }
 }
 
-impl UrsTaxidMapping {
-    pub fn from_urs_file(path: &Path) -> Result<Self, Error> {
-        let file = File::open(path)?;
-        let mut reader = BufReader::new(file);
-
-        let mut mapping: FnvHashMap<u64, FnvHashSet<u64>> = FnvHashMap::default();
-        let mut buf = String::new();
-        loop {
-            match reader.read_line(&mut buf)? {
-                0 => break,
-                _ => {
-                    let urs_taxid: UrsTaxid = buf.trim_end().parse()?;
-                    let set = mapping.entry(urs_taxid.urs()).or_insert(FnvHashSet::default());
-                    set.insert(urs_taxid.taxid());
-                    buf.clear();
-                },
-            }
-        }
+impl<S: UrsTaxidStore + ?Sized> UrsTaxidStore for Box<S> {
+    fn urs_taxids(&self, urs: &Urs) -> Vec<UrsTaxid> {
+        (**self).urs_taxids(urs)
+    }
 
-        Ok(Self {
-            mapping,
-        })
+    fn contains(&self, urs_taxid: &UrsTaxid) -> bool {
+        (**self).contains(urs_taxid)
     }
 
-    pub fn urs_taxids(&self, urs: &Urs) -> Vec<UrsTaxid> {
+    fn contains_urs(&self, urs: &Urs) -> bool {
+        (**self).contains_urs(urs)
+    }
+}
+
+/// Load the URS_taxids from either a text file with one URS_taxid per line, '-' meaning stdin,
+/// or a table built by urs-table, which is used without reading it all into memory.
+pub fn load_store(path: &Path) -> Result<Box<dyn UrsTaxidStore>, Error> {
+    let is_table = table::is_table(path).map_err(table::Error::Io)?;
+    if is_table {
+        return Ok(Box::new(UrsTaxidTable::open(path)?));
+    }
+    Ok(Box::new(UrsTaxidMapping::from_urs_file(path)?))
+}
+
+#[derive(Default)]
+pub struct UrsTaxidMapping {
+    mapping: FnvHashMap<u64, FnvHashSet<u64>>,
+}
+
+impl UrsTaxidStore for UrsTaxidMapping {
+    fn urs_taxids(&self, urs: &Urs) -> Vec<UrsTaxid> {
         let id: u64 = urs.into();
         match self.mapping.get(&id) {
             None => Vec::with_capacity(0),
@@ -79,4 +85,89 @@ impl UrsTaxidMapping {
             },
         }
     }
+
+    fn contains(&self, urs_taxid: &UrsTaxid) -> bool {
+        match self.mapping.get(&urs_taxid.urs()) {
+            None => false,
+            Some(ts) => ts.contains(&urs_taxid.taxid()),
+        }
+    }
+
+    fn contains_urs(&self, urs: &Urs) -> bool {
+        self.mapping.contains_key(&urs.into())
+    }
+}
+
+impl UrsTaxidMapping {
+    /// Load the URS_taxids in a file, one per line. The URS may be in any case.
+    pub fn from_urs_file(path: &Path) -> Result<Self, lines::Error> {
+        let mut mapping = Self::default();
+        lines::each_parsed(path, UrsTaxid::parse_any_case, |urs_taxid| mapping.add(&urs_taxid))?;
+        Ok(mapping)
+    }
+
+    pub fn add(&mut self, urs_taxid: &UrsTaxid) {
+        let set = self.mapping.entry(urs_taxid.urs()).or_default();
+        set.insert(urs_taxid.taxid());
+    }
+}
+
+impl UrsTaxidStore for UrsTaxidTable {
+    fn urs_taxids(&self, urs: &Urs) -> Vec<UrsTaxid> {
+        UrsTaxidTable::urs_taxids(self, urs)
+    }
+
+    fn contains(&self, urs_taxid: &UrsTaxid) -> bool {
+        UrsTaxidTable::contains(self, urs_taxid)
+    }
+
+    fn contains_urs(&self, urs: &Urs) -> bool {
+        UrsTaxidTable::contains_urs(self, urs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    use rnc_test_utils::temp_file_with;
+    use tempfile::NamedTempFile;
+
+    fn check(store: &dyn UrsTaxidStore) -> Result<(), Box<dyn Error>> {
+        let urs: Urs = "URS0000762A36".parse()?;
+        let mut found = store.urs_taxids(&urs);
+        found.sort();
+        assert_eq!(found, vec![UrsTaxid::new(0x762A36, 562), UrsTaxid::new(0x762A36, 9606)]);
+        assert!(store.contains(&"URS0000762A36_9606".parse()?));
+        assert!(!store.contains(&"URS0000762A36_10090".parse()?));
+        assert!(store.contains_urs(&"URS0000000001".parse()?));
+        assert!(!store.contains_urs(&"URS0000000002".parse()?));
+        assert!(store.urs_taxids(&"URS0000000002".parse()?).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn can_load_text_and_table_stores() -> Result<(), Box<dyn Error>> {
+        let ids = vec!["URS0000762A36_9606", "URS0000762A36_562", "URS0000000001_9606"];
+        let text = temp_file_with(ids.clone())?;
+        check(load_store(text.path())?.as_ref())?;
+
+        let table = NamedTempFile::new()?;
+        let parsed = ids.iter().map(|id| id.parse()).collect::<Result<Vec<UrsTaxid>, _>>()?;
+        table::write_urs_taxids(table.as_file(), parsed)?;
+        check(load_store(table.path())?.as_ref())?;
+        Ok(())
+    }
+
+    #[test]
+    fn reports_the_line_which_failed() -> Result<(), Box<dyn Error>> {
+        let text = temp_file_with(vec!["URS0000762A36_9606", "URS0000762A36"])?;
+        let message = match load_store(text.path()) {
+            Err(super::Error::Lines(err)) => err.to_string(),
+            _ => panic!("Loaded an invalid file"),
+        };
+        assert!(message.starts_with("Could not parse line 2 of"), "{}", message);
+        Ok(())
+    }
 }
@@ -1,5 +1,4 @@
 use std::{
-    fs::File,
     io,
     io::BufRead,
     path::Path,
@@ -62,13 +61,10 @@ impl<'a> XmlIterator<&'a [u8]> {
     }
 }
 
-impl XmlIterator<io::BufReader<File>> {
-    pub fn from_file<P: AsRef<Path>>(
-        path: P,
-    ) -> Result<XmlIterator<io::BufReader<File>>, XmlError> {
-        let file = File::open(path)?;
-        let reader = io::BufReader::new(file);
-        Ok(Self::from_reader(reader))
+impl XmlIterator<Box<dyn BufRead>> {
+    /// Read the references in a file, '-' means stdin, which may be compressed.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<XmlIterator<Box<dyn BufRead>>, XmlError> {
+        Ok(Self::from_reader(rnc_utils::buf_reader(path.as_ref())?))
     }
 }
 
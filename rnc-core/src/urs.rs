@@ -1,17 +1,41 @@
+use std::fmt;
 use std::path::{Path, PathBuf};
 use std::str;
 use std::str::FromStr;
 
 use regex::Regex;
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use thiserror::Error;
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+/// The most hex digits a URS can have, this is the length of the padded form.
+const MAX_DIGITS: usize = 10;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    #[error("URS {0:?} does not start with 'URS'")]
+    MissingPrefix(String),
+
+    #[error("URS {0:?} has no digits after 'URS'")]
+    MissingDigits(String),
+
+    #[error("URS {0:?} has more than 10 digits")]
+    TooManyDigits(String),
+
+    #[error("URS {0:?} contains something other than uppercase hex digits")]
+    InvalidDigits(String),
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, PartialOrd, Ord)]
 pub struct Urs(u64);
 
 impl FromStr for Urs {
-    type Err = std::num::ParseIntError;
+    type Err = Error;
 
+    /// Parse a URS in the padded form, like `URS0000C0472E`, or the short form, like
+    /// `URSC0472E`. The whole URS must be uppercase, see `Urs::parse_any_case` to allow either.
     fn from_str(raw: &str) -> Result<Self, Self::Err> {
-        u64::from_str_radix(&raw[3..], 16).map(|s| Urs(s))
+        Self::parse(raw, false)
     }
 }
 
@@ -39,9 +63,71 @@ impl From<&Urs> for u64 {
     }
 }
 
+impl fmt::Display for Urs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "URS{:010X}", self.0)
+    }
+}
+
+impl Serialize for Urs {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+struct UrsVisitor;
+
+impl<'de> Visitor<'de> for UrsVisitor {
+    type Value = Urs;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a URS like URS0000C0472E")
+    }
+
+    fn visit_str<E: de::Error>(self, raw: &str) -> Result<Urs, E> {
+        raw.parse().map_err(E::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Urs {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(UrsVisitor)
+    }
+}
+
 impl Urs {
-    pub fn to_string(&self) -> String {
-        format!("URS{:010X}", self.0)
+    /// Parse a URS like `from_str`, but allow lowercase letters anywhere in it.
+    pub fn parse_any_case(raw: &str) -> Result<Self, Error> {
+        Self::parse(raw, true)
+    }
+
+    fn parse(raw: &str, any_case: bool) -> Result<Self, Error> {
+        let prefix = raw.get(..3).filter(|p| match any_case {
+            true => p.eq_ignore_ascii_case("URS"),
+            false => *p == "URS",
+        });
+        if prefix.is_none() {
+            return Err(Error::MissingPrefix(raw.to_string()));
+        }
+
+        let digits = &raw[3..];
+        if digits.is_empty() {
+            return Err(Error::MissingDigits(raw.to_string()));
+        }
+        if digits.len() > MAX_DIGITS {
+            return Err(Error::TooManyDigits(raw.to_string()));
+        }
+        let valid = digits.bytes().all(|b| match b {
+            b'0'..=b'9' | b'A'..=b'F' => true,
+            b'a'..=b'f' => any_case,
+            _ => false,
+        });
+        if !valid {
+            return Err(Error::InvalidDigits(raw.to_string()));
+        }
+        u64::from_str_radix(digits, 16)
+            .map(Urs)
+            .map_err(|_| Error::InvalidDigits(raw.to_string()))
     }
 
     pub fn short_urs(&self) -> String {
@@ -76,6 +162,7 @@ impl Urs {
 
 #[cfg(test)]
 mod tests {
+    use super::Error::*;
     use super::*;
     use std::error::Error;
 
@@ -99,6 +186,40 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn rejects_invalid_urs() {
+        assert_eq!("".parse::<Urs>(), Err(MissingPrefix(String::from(""))));
+        assert_eq!("AB".parse::<Urs>(), Err(MissingPrefix(String::from("AB"))));
+        assert_eq!("ABC123".parse::<Urs>(), Err(MissingPrefix(String::from("ABC123"))));
+        assert_eq!("é€".parse::<Urs>(), Err(MissingPrefix(String::from("é€"))));
+        assert_eq!("URS".parse::<Urs>(), Err(MissingDigits(String::from("URS"))));
+        assert_eq!(
+            "URS00000001AAB82D".parse::<Urs>(),
+            Err(TooManyDigits(String::from("URS00000001AAB82D")))
+        );
+        assert_eq!("URS+1".parse::<Urs>(), Err(InvalidDigits(String::from("URS+1"))));
+        assert_eq!("URS00é".parse::<Urs>(), Err(InvalidDigits(String::from("URS00é"))));
+        assert!("urs0000C0472E".parse::<Urs>().is_err());
+        assert!("URS0000c0472e".parse::<Urs>().is_err());
+    }
+
+    #[test]
+    fn can_parse_any_case() -> Result<(), Box<dyn Error>> {
+        assert_eq!(Urs::parse_any_case("urs0000c0472e")?, Urs(12601134));
+        assert_eq!(Urs::parse_any_case("URSc0472E")?, Urs(12601134));
+        assert!(Urs::parse_any_case("abc0000c0472e").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn can_serialize_urs() -> Result<(), Box<dyn Error>> {
+        assert_eq!(serde_json::to_string(&Urs(12601134))?, "\"URS0000C0472E\"");
+        assert_eq!(serde_json::from_str::<Urs>("\"URS0000C0472E\"")?, Urs(12601134));
+        assert!(serde_json::from_str::<Urs>("\"ABC\"").is_err());
+        assert!(Urs(9) < Urs(10));
+        Ok(())
+    }
+
     #[test]
     fn matches_urs() {
         assert_eq!(Urs::looks_like_urs("URS00000001AAB82D"), false);
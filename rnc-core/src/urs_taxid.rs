@@ -1,22 +1,40 @@
 use std::{
+    fmt,
     str,
     str::FromStr,
 };
 
+use serde::{
+    de::{
+        self,
+        Deserializer,
+        Visitor,
+    },
+    Deserialize,
+    Serialize,
+    Serializer,
+};
+
 use thiserror::Error;
 
-use crate::urs::Urs;
+use crate::urs::{
+    self,
+    Urs,
+};
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum Error {
-    #[error("Could not parse urs: {0}")]
-    CannotParseUrs(String),
+    #[error("Could not parse urs of {0:?}: {1}")]
+    CannotParseUrs(String, #[source] urs::Error),
 
-    #[error("Could not parse taxid: {0}")]
+    #[error("No taxid in {0:?}, it must be like URS0000C0472E_9606")]
+    MissingTaxid(String),
+
+    #[error("Could not parse taxid of {0:?}")]
     CannotParseTaxid(String),
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, PartialOrd, Ord)]
 pub struct UrsTaxid(u64, u64);
 
 impl UrsTaxid {
@@ -24,10 +42,6 @@ impl UrsTaxid {
         UrsTaxid(urs, taxid)
     }
 
-    pub fn to_string(&self) -> String {
-        format!("URS{:010X}_{}", self.0, self.1)
-    }
-
     pub fn urs(&self) -> u64 {
         self.0
     }
@@ -35,25 +49,81 @@ impl UrsTaxid {
     pub fn taxid(&self) -> u64 {
         self.1
     }
+
+    /// Parse a URS_taxid like `from_str`, but allow lowercase letters anywhere in the URS. This
+    /// accepts ids like `urs0000c0472e_9606` which were accepted before parsing became strict,
+    /// so it is meant for reading files of ids.
+    pub fn parse_any_case(raw: &str) -> Result<Self, Error> {
+        Self::parse(raw, true)
+    }
+
+    fn parse(raw: &str, any_case: bool) -> Result<Self, Error> {
+        let (raw_urs, raw_taxid) =
+            raw.split_once('_').ok_or_else(|| Error::MissingTaxid(raw.to_string()))?;
+        let urs = match any_case {
+            true => Urs::parse_any_case(raw_urs),
+            false => raw_urs.parse(),
+        }
+        .map_err(|err| Error::CannotParseUrs(raw.to_string(), err))?;
+
+        if raw_taxid.is_empty() {
+            return Err(Error::MissingTaxid(raw.to_string()));
+        }
+        if !raw_taxid.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(Error::CannotParseTaxid(raw.to_string()));
+        }
+        let taxid =
+            raw_taxid.parse::<u64>().map_err(|_| Error::CannotParseTaxid(raw.to_string()))?;
+        Ok(Self(urs.into(), taxid))
+    }
 }
 
 impl FromStr for UrsTaxid {
     type Err = Error;
 
+    /// Parse a URS_taxid like `URS0000C0472E_9606`. The URS may be in the short form, like
+    /// `URSC0472E_9606`, and the taxid must be only digits. The URS must be uppercase, see
+    /// `UrsTaxid::parse_any_case` to allow either.
     fn from_str(raw: &str) -> Result<Self, Self::Err> {
-        let (raw_urs, raw_taxid) = raw.split_at(14);
-        let urs = u64::from_str_radix(&raw_urs[3..13], 16)
-            .map_err(|_| Error::CannotParseUrs(raw.to_string()))?;
+        Self::parse(raw, false)
+    }
+}
 
-        let taxid =
-            raw_taxid.parse::<u64>().map_err(|_| Error::CannotParseTaxid(raw_taxid.to_string()))?;
-        Ok(Self(urs, taxid))
+impl fmt::Display for UrsTaxid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "URS{:010X}_{}", self.0, self.1)
+    }
+}
+
+impl Serialize for UrsTaxid {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+struct UrsTaxidVisitor;
+
+impl<'de> Visitor<'de> for UrsTaxidVisitor {
+    type Value = UrsTaxid;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a URS_taxid like URS0000C0472E_9606")
+    }
+
+    fn visit_str<E: de::Error>(self, raw: &str) -> Result<UrsTaxid, E> {
+        raw.parse().map_err(E::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for UrsTaxid {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(UrsTaxidVisitor)
     }
 }
 
 impl From<&UrsTaxid> for String {
     fn from(urs: &UrsTaxid) -> String {
-        format!("URS{:010X}_{}", urs.0, urs.1)
+        urs.to_string()
     }
 }
 
@@ -71,6 +141,7 @@ impl From<UrsTaxid> for Urs {
 
 #[cfg(test)]
 mod tests {
+    use super::Error::*;
     use super::*;
     use std::error::Error;
 
@@ -84,4 +155,46 @@ mod tests {
         assert_eq!("URS00008C3642_9606".parse::<UrsTaxid>()?, UrsTaxid(9188930, 9606));
         Ok(())
     }
+
+    #[test]
+    fn can_convert_short_urs_taxids() -> Result<(), Box<dyn Error>> {
+        assert_eq!("URS9_1".parse::<UrsTaxid>()?, UrsTaxid(9, 1));
+        assert_eq!("URSC0472E_12445".parse::<UrsTaxid>()?, UrsTaxid(12601134, 12445));
+        Ok(())
+    }
+
+    #[test]
+    fn can_parse_any_case() -> Result<(), Box<dyn Error>> {
+        assert!("urs0000c0472e_12445".parse::<UrsTaxid>().is_err());
+        assert_eq!(UrsTaxid::parse_any_case("urs0000c0472e_12445")?, UrsTaxid(12601134, 12445));
+        assert_eq!(UrsTaxid::parse_any_case("URSc0472E_12445")?, UrsTaxid(12601134, 12445));
+        assert!(matches!(UrsTaxid::parse_any_case("abc0000c0472e_1"), Err(CannotParseUrs(_, _))));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_urs_taxids() {
+        let error = |raw: &str| raw.parse::<UrsTaxid>().unwrap_err();
+        assert_eq!(error(""), MissingTaxid(String::from("")));
+        assert_eq!(error("URS0000C0472E"), MissingTaxid(String::from("URS0000C0472E")));
+        assert_eq!(error("URS0000C0472E_"), MissingTaxid(String::from("URS0000C0472E_")));
+        assert!(matches!(error("ABC_1"), CannotParseUrs(_, _)));
+        assert!(matches!(error("URS00000001AAB82D_1"), CannotParseUrs(_, _)));
+        assert_eq!(error("URS1_9606a"), CannotParseTaxid(String::from("URS1_9606a")));
+        assert_eq!(error("URS1_9606_1"), CannotParseTaxid(String::from("URS1_9606_1")));
+        assert_eq!(error("URS1_+9606"), CannotParseTaxid(String::from("URS1_+9606")));
+        assert_eq!(error("URS1_ 9606"), CannotParseTaxid(String::from("URS1_ 9606")));
+    }
+
+    #[test]
+    fn can_display_and_serialize_urs_taxids() -> Result<(), Box<dyn Error>> {
+        let urs_taxid = UrsTaxid(12601134, 12445);
+        assert_eq!(urs_taxid.to_string(), "URS0000C0472E_12445");
+        assert_eq!(serde_json::to_string(&urs_taxid)?, "\"URS0000C0472E_12445\"");
+        assert_eq!(serde_json::from_str::<UrsTaxid>("\"URSC0472E_12445\"")?, urs_taxid);
+        assert!(serde_json::from_str::<UrsTaxid>("\"URSC0472E\"").is_err());
+        assert!(UrsTaxid(1, 10) > UrsTaxid(1, 9));
+        assert!(UrsTaxid(2, 1) > UrsTaxid(1, 9));
+        Ok(())
+    }
 }
@@ -4,38 +4,257 @@ use std::io::prelude::*;
 use std::io::{BufReader, BufWriter};
 use std::path::Path;
 
+use bzip2::bufread::MultiBzDecoder;
+use bzip2::write::BzEncoder;
+use flate2::bufread::MultiGzDecoder;
+use flate2::write::GzEncoder;
+
+/// The buffer size of `buf_writer`, the same as the default of `BufWriter`.
+const DEFAULT_CAPACITY: usize = 8 * 1024;
+
+/// The compression formats that the readers and writers in this crate will handle
+/// transparently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Bzip2,
+    Zstd,
+}
+
+impl Compression {
+    /// Detect the compression of some data from the first few bytes of it.
+    pub fn from_magic(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if bytes.starts_with(b"BZh") {
+            Compression::Bzip2
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+
+    /// Select the compression to use for a file based off the extension of the filename.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("bz2") => Compression::Bzip2,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+}
+
+fn decompressed<R: BufRead + 'static>(mut reader: R) -> io::Result<Box<dyn BufRead>> {
+    let compression = Compression::from_magic(reader.fill_buf()?);
+    Ok(match compression {
+        Compression::None => Box::new(reader),
+        Compression::Gzip => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+        Compression::Bzip2 => Box::new(BufReader::new(MultiBzDecoder::new(reader))),
+        Compression::Zstd => {
+            Box::new(BufReader::new(zstd::stream::read::Decoder::with_buffer(reader)?))
+        },
+    })
+}
+
+/// The compressor, if any, that written data goes through before reaching the file.
+enum Encoder {
+    Plain(Box<dyn Write>),
+    Gzip(GzEncoder<Box<dyn Write>>),
+    Bzip2(BzEncoder<Box<dyn Write>>),
+    Zstd(zstd::stream::write::Encoder<'static, Box<dyn Write>>),
+}
+
+impl Encoder {
+    fn new(writer: Box<dyn Write>, compression: Compression) -> io::Result<Self> {
+        Ok(match compression {
+            Compression::None => Encoder::Plain(writer),
+            Compression::Gzip => {
+                Encoder::Gzip(GzEncoder::new(writer, flate2::Compression::default()))
+            },
+            Compression::Bzip2 => {
+                Encoder::Bzip2(BzEncoder::new(writer, bzip2::Compression::default()))
+            },
+            Compression::Zstd => Encoder::Zstd(zstd::stream::write::Encoder::new(writer, 0)?),
+        })
+    }
+
+    /// Write the end of the compressed stream, if any, and flush the file.
+    fn finish(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(writer) => writer.flush(),
+            Encoder::Gzip(encoder) => {
+                encoder.try_finish()?;
+                encoder.get_mut().flush()
+            },
+            Encoder::Bzip2(encoder) => {
+                encoder.try_finish()?;
+                encoder.get_mut().flush()
+            },
+            Encoder::Zstd(encoder) => {
+                encoder.do_finish()?;
+                encoder.get_mut().flush()
+            },
+        }
+    }
+}
+
+impl Write for Encoder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Plain(writer) => writer.write(buf),
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            Encoder::Bzip2(encoder) => encoder.write(buf),
+            Encoder::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(writer) => writer.flush(),
+            Encoder::Gzip(encoder) => encoder.flush(),
+            Encoder::Bzip2(encoder) => encoder.flush(),
+            Encoder::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// A writer to a file or stdout which may compress the data. A compressed file is only complete
+/// once `finish` has been called, which reports any error writing the end of the stream. If it
+/// is dropped without calling `finish`, the stream is still completed, but errors are lost.
+pub struct Writer {
+    inner: BufWriter<Encoder>,
+    finished: bool,
+}
+
+impl Writer {
+    fn new(writer: Box<dyn Write>, compression: Compression, capacity: usize) -> io::Result<Self> {
+        Ok(Self {
+            inner: BufWriter::with_capacity(capacity, Encoder::new(writer, compression)?),
+            finished: false,
+        })
+    }
+
+    /// Flush all buffered data and complete the compressed stream, if any.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.finished = true;
+        self.inner.flush()?;
+        self.inner.get_mut().finish()
+    }
+}
+
+impl Write for Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl Drop for Writer {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.inner.flush().and_then(|_| self.inner.get_mut().finish());
+        }
+    }
+}
+
+/// Create a buffered reader of the given file, '-' means stdin. If the data is gzip, bzip2 or
+/// zstd compressed, detected from the leading bytes, it will be decompressed while reading.
 pub fn buf_reader(filename: &Path) -> io::Result<Box<dyn BufRead>> {
     if filename == Path::new("-") {
-        let stdin = io::stdin();
-        stdin.lock();
-        return Ok(Box::new(BufReader::new(stdin)));
+        return decompressed(BufReader::new(io::stdin()));
     }
     let file = File::open(filename)?;
-    Ok(Box::new(BufReader::new(file)))
+    decompressed(BufReader::new(file))
 }
 
-pub fn buf_writer(filename: &Path) -> io::Result<Box<dyn Write>> {
+/// Create a buffered writer to the given file, '-' means stdout. If the filename ends with
+/// '.gz', '.bz2' or '.zst' the data will be compressed with that format. Call `finish` once
+/// done writing, so errors completing the file are reported.
+pub fn buf_writer(filename: &Path) -> io::Result<Writer> {
     if filename == Path::new("-") {
-        return Ok(Box::new(BufWriter::new(io::stdout())));
+        return Writer::new(Box::new(io::stdout()), Compression::None, DEFAULT_CAPACITY);
     }
     let file = File::create(filename)?;
-    Ok(Box::new(BufWriter::new(file)))
+    Writer::new(Box::new(file), Compression::from_path(filename), DEFAULT_CAPACITY)
 }
 
-pub fn writer(filename: &Path) -> io::Result<Box<dyn Write>> {
+/// Like `buf_writer` but without any buffering, for callers which buffer themselves.
+pub fn writer(filename: &Path) -> io::Result<Writer> {
     if filename == Path::new("-") {
-        return Ok(Box::new(io::stdout()));
+        return Writer::new(Box::new(io::stdout()), Compression::None, 0);
     }
     let file = File::create(filename)?;
-    Ok(Box::new(file))
+    Writer::new(Box::new(file), Compression::from_path(filename), 0)
 }
 
 pub fn reader(filename: &Path) -> io::Result<Box<dyn Read>> {
-    if filename == Path::new("-") {
-        let stdin = io::stdin();
-        stdin.lock();
-        return Ok(Box::new(stdin));
+    Ok(Box::new(buf_reader(filename)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    use tempfile::tempdir;
+
+    fn round_trip(name: &str) -> Result<(String, Vec<u8>), Box<dyn Error>> {
+        let dir = tempdir()?;
+        let path = dir.path().join(name);
+        let mut writer = buf_writer(&path)?;
+        writeln!(&mut writer, "URS0000000001_9606")?;
+        writeln!(&mut writer, "URS0000000002_562")?;
+        writer.finish()?;
+        let raw = std::fs::read(&path)?;
+        let mut data = String::new();
+        buf_reader(&path)?.read_to_string(&mut data)?;
+        Ok((data, raw))
+    }
+
+    #[test]
+    fn detects_compression_from_extension() {
+        assert_eq!(Compression::from_path(Path::new("a.json.gz")), Compression::Gzip);
+        assert_eq!(Compression::from_path(Path::new("a.json.bz2")), Compression::Bzip2);
+        assert_eq!(Compression::from_path(Path::new("a.json.zst")), Compression::Zstd);
+        assert_eq!(Compression::from_path(Path::new("a.json")), Compression::None);
+        assert_eq!(Compression::from_path(Path::new("-")), Compression::None);
+    }
+
+    #[test]
+    fn can_read_and_write_all_formats() -> Result<(), Box<dyn Error>> {
+        let expected = "URS0000000001_9606\nURS0000000002_562\n";
+        for (name, compression) in &[
+            ("plain.txt", Compression::None),
+            ("data.gz", Compression::Gzip),
+            ("data.bz2", Compression::Bzip2),
+            ("data.zst", Compression::Zstd),
+        ] {
+            let (data, raw) = round_trip(name)?;
+            assert_eq!(data, expected);
+            assert_eq!(Compression::from_magic(&raw), *compression);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn completes_streams_when_dropped() -> Result<(), Box<dyn Error>> {
+        let dir = tempdir()?;
+        for name in &["data.gz", "data.bz2", "data.zst"] {
+            let path = dir.path().join(name);
+            {
+                let mut writer = buf_writer(&path)?;
+                writeln!(&mut writer, "URS0000000001_9606")?;
+            }
+            let mut data = String::new();
+            buf_reader(&path)?.read_to_string(&mut data)?;
+            assert_eq!(data, "URS0000000001_9606\n");
+        }
+        Ok(())
     }
-    let file = File::open(filename)?;
-    Ok(Box::new(file))
 }
@@ -27,6 +27,27 @@ enum Subcommand {
         /// Where to write normalized JSON data to, '-' means stdout.
         output_file: PathBuf,
     },
+
+    /// Normalize search data directly from a `kv` index, instead of from the output of a
+    /// `kv lookup`, and turn it into a stream of JSON data suitable for the pipeline to process.
+    NormalizeIndex {
+        #[structopt(short, long, parse(from_os_str))]
+        /// Filename of the keys to normalize, one per line, '-' means stdin. If not given,
+        /// every key in the index is normalized.
+        keys: Option<PathBuf>,
+
+        #[structopt(parse(from_os_str))]
+        /// Path to the `kv` index of search data.
+        index: PathBuf,
+
+        #[structopt(parse(from_os_str))]
+        /// Filename of the SO term tree metadata.
+        so_term_tree: PathBuf,
+
+        #[structopt(parse(from_os_str))]
+        /// Where to write normalized JSON data to, '-' means stdout.
+        output_file: PathBuf,
+    },
 }
 
 #[derive(Debug, StructOpt)]
@@ -62,6 +83,12 @@ fn main() -> Result<()> {
             so_term_tree,
             output_file,
         } => normalize::write_file(&input_file, &so_term_tree, &output_file)?,
+        Subcommand::NormalizeIndex {
+            keys,
+            index,
+            so_term_tree,
+            output_file,
+        } => normalize::write_index(&index, keys.as_deref(), &so_term_tree, &output_file)?,
     }
 
     Ok(())
@@ -4,7 +4,9 @@ use std::{
 };
 
 use serde::{
+    de,
     Deserialize,
+    Deserializer,
     Serialize,
 };
 
@@ -58,9 +60,17 @@ use crate::normalize::ds::{
     so_tree,
 };
 
+/// The ids in the exported data have always been read without checking their case, so keep
+/// accepting a lowercase URS.
+fn any_case_urs_taxid<'de, D: Deserializer<'de>>(deserializer: D) -> Result<UrsTaxid, D::Error> {
+    let raw = String::deserialize(deserializer)?;
+    UrsTaxid::parse_any_case(&raw).map_err(de::Error::custom)
+}
+
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Raw {
-    id: String,
+    #[serde(deserialize_with = "any_case_urs_taxid")]
+    id: UrsTaxid,
     base: Vec<Basic>,
     cross_references: Vec<CrossReference>,
     crs: Vec<Crs>,
@@ -127,9 +137,9 @@ pub struct Raw {
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Normalized {
-    urs: String,
+    urs: Urs,
     taxid: u64,
-    urs_taxid: String,
+    urs_taxid: UrsTaxid,
     short_urs: String,
     deleted: String,
 
@@ -157,21 +167,16 @@ pub struct Normalized {
 }
 
 impl Raw {
-    pub fn urs(&self) -> anyhow::Result<String> {
-        let ut: UrsTaxid = self.id.parse()?;
-        let urs: Urs = ut.into();
-        Ok(urs.to_string())
+    pub fn urs(&self) -> Urs {
+        Urs::from(&self.id)
     }
 
-    pub fn taxid(&self) -> anyhow::Result<u64> {
-        let ut: UrsTaxid = self.id.parse()?;
-        Ok(ut.taxid())
+    pub fn taxid(&self) -> u64 {
+        self.id.taxid()
     }
 
-    pub fn short_urs(&self) -> anyhow::Result<String> {
-        let ut: UrsTaxid = self.id.parse()?;
-        let urs: Urs = ut.into();
-        Ok(urs.short_urs())
+    pub fn short_urs(&self) -> String {
+        self.urs().short_urs()
     }
 }
 
@@ -186,9 +191,9 @@ impl Normalized {
 
         Ok(Self {
             urs_taxid: raw.id.clone(),
-            urs: raw.urs()?,
-            taxid: raw.taxid()?,
-            short_urs: raw.short_urs()?,
+            urs: raw.urs(),
+            taxid: raw.taxid(),
+            short_urs: raw.short_urs(),
             deleted: String::from("N"),
 
             so_rna_type_tree,
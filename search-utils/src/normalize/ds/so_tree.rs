@@ -1,10 +1,6 @@
 use std::{
     collections::HashMap,
-    fs::File,
-    io::{
-        BufRead,
-        BufReader,
-    },
+    io::BufRead,
     path::Path,
 };
 
@@ -31,8 +27,7 @@ struct SoLine {
 
 pub fn load(filename: &Path) -> Result<HashMap<String, SoTree>> {
     let mut map: HashMap<String, SoTree> = HashMap::new();
-    let file = File::open(filename)?;
-    let reader = BufReader::new(file);
+    let reader = rnc_utils::buf_reader(filename)?;
     for line in reader.lines() {
         let line = line?;
         let entry: SoLine = serde_json::from_str(&line)?;
@@ -1,9 +1,19 @@
 use anyhow::{
+    anyhow,
     Context,
     Result,
 };
 
-use std::path::Path;
+use std::{
+    collections::HashMap,
+    io::{
+        BufRead,
+        Write,
+    },
+    path::Path,
+};
+
+use kv::store::Store;
 
 pub mod ds;
 pub mod utils;
@@ -14,10 +24,26 @@ use crate::normalize::ds::{
         Raw,
     },
     so_tree,
+    so_tree::SoTree,
 };
 
+/// The number of keys to look up in the index at once.
+const BATCH_SIZE: usize = 1000;
+
+fn write_normalized(
+    raw: &Raw,
+    so_tree: &HashMap<String, SoTree>,
+    writer: &mut dyn Write,
+) -> Result<()> {
+    let norm =
+        Normalized::new(raw, so_tree).with_context(|| format!("Normalizing: {:?}", &raw))?;
+    serde_json::to_writer(&mut *writer, &norm)?;
+    writeln!(writer)?;
+    Ok(())
+}
+
 pub fn write_file(input_file: &Path, so_term_tree: &Path, output_file: &Path) -> Result<()> {
-    let so_tree = so_tree::load(&so_term_tree)?;
+    let so_tree = so_tree::load(so_term_tree)?;
     let mut reader = rnc_utils::buf_reader(input_file)?;
     let mut writer = rnc_utils::buf_writer(output_file)?;
     let mut buf = String::new();
@@ -26,14 +52,67 @@ pub fn write_file(input_file: &Path, so_term_tree: &Path, output_file: &Path) ->
             0 => break,
             _ => {
                 let raw: Raw = serde_json::from_str(&buf)?;
-                let norm = Normalized::new(&raw, &so_tree)
-                    .with_context(|| format!("Normalizing: {:?}", &raw))?;
-                serde_json::to_writer(&mut writer, &norm)?;
-                writeln!(&mut writer)?;
+                write_normalized(&raw, &so_tree, &mut writer)?;
                 buf.clear();
             },
         }
     }
 
+    writer.finish()?;
+    Ok(())
+}
+
+/// Normalize the data for entries directly from a `kv` index. If a key file is given only the
+/// keys in it are normalized, otherwise every key in the index is. Keys are looked up in
+/// batches, and blank lines in the key file are skipped.
+pub fn write_index(
+    index: &Path,
+    key_file: Option<&Path>,
+    so_term_tree: &Path,
+    output_file: &Path,
+) -> Result<()> {
+    let so_tree = so_tree::load(so_term_tree)?;
+    let store = Store::open(index)?;
+    let mut writer = rnc_utils::buf_writer(output_file)?;
+
+    let keys: Box<dyn Iterator<Item = Result<String>> + '_> = match key_file {
+        Some(path) => {
+            let reader = rnc_utils::buf_reader(path)?;
+            let keys = reader.lines().filter_map(|line| match line {
+                Ok(line) if line.trim().is_empty() => None,
+                Ok(line) => Some(Ok(line.trim_end().to_string())),
+                Err(err) => Some(Err(err.into())),
+            });
+            Box::new(keys)
+        },
+        None => Box::new(store.keys()),
+    };
+
+    let mut block = Vec::with_capacity(BATCH_SIZE);
+    for key in keys {
+        block.push(key?);
+        if block.len() == BATCH_SIZE {
+            write_batch(&store, &block, &so_tree, &mut writer)?;
+            block.clear();
+        }
+    }
+    write_batch(&store, &block, &so_tree, &mut writer)?;
+
+    writer.finish()?;
+    Ok(())
+}
+
+fn write_batch(
+    store: &Store,
+    keys: &[String],
+    so_tree: &HashMap<String, SoTree>,
+    writer: &mut dyn Write,
+) -> Result<()> {
+    for (key, data) in keys.iter().zip(store.grouped_batch(keys)?) {
+        let data = data.ok_or_else(|| anyhow!("No data found for key {}", key))?;
+        let raw: Raw = serde_json::from_value(serde_json::Value::Object(data))
+            .with_context(|| format!("Loading data for {}", &key))?;
+        write_normalized(&raw, so_tree, writer)?;
+    }
     Ok(())
 }
@@ -0,0 +1,176 @@
+use std::{
+    error::Error,
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+    process::Output,
+};
+
+use serde_json::{
+    json,
+    Value,
+};
+
+use tempfile::{
+    tempdir,
+    NamedTempFile,
+};
+
+use rnc_test_utils::{
+    temp_file_with,
+    Jsonl,
+};
+
+use kv::store::{
+    self,
+    Spec,
+};
+
+const FAMILIES: &[&str] = &[
+    "base",
+    "cross_references",
+    "crs",
+    "feedback",
+    "go_annotations",
+    "interacting_proteins",
+    "interacting_rnas",
+    "precompute",
+    "qa_status",
+    "r2dt",
+    "references",
+    "rfam_hits",
+];
+
+fn temp_jsonl(values: Vec<Value>) -> io::Result<NamedTempFile> {
+    let lines: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+    temp_file_with(lines.iter().map(|line| line.as_str()).collect())
+}
+
+fn example_index(dir: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    let db_path = dir.join("index.db");
+    let spec = Spec::new(&db_path);
+    for family in FAMILIES {
+        let values = match *family {
+            "base" => vec![
+                json!({"id": "URS0000614226_291828", "length": 181, "md5": "1b40"}),
+                json!({"id": "URS0000000001_9606", "length": 10, "md5": "aa"}),
+            ],
+            "precompute" => vec![
+                json!({
+                    "id": "URS0000614226_291828",
+                    "description": "16S rRNA",
+                    "rna_type": "rRNA",
+                    "has_coordinates": false,
+                    "so_rna_type": "SO:0000650",
+                    "databases": "ENA",
+                }),
+                json!({
+                    "id": "URS0000000001_9606",
+                    "description": "A tRNA",
+                    "rna_type": "tRNA",
+                    "has_coordinates": true,
+                    "so_rna_type": "SO:0000253",
+                    "databases": "ENA,GtRNAdb",
+                }),
+            ],
+            "qa_status" => vec![
+                json!({
+                    "id": "URS0000614226_291828",
+                    "has_issue": true,
+                    "incomplete_sequence": true,
+                    "missing_rfam_match": false,
+                    "possible_contamination": false,
+                }),
+                json!({
+                    "id": "URS0000000001_9606",
+                    "has_issue": false,
+                    "incomplete_sequence": false,
+                    "missing_rfam_match": false,
+                    "possible_contamination": false,
+                }),
+            ],
+            _ => vec![],
+        };
+        let file = temp_jsonl(values)?;
+        store::index(&spec, family, file.path())?;
+    }
+    Ok(db_path)
+}
+
+fn normalize_index(
+    keys: Option<&Path>,
+    index: &Path,
+    so_tree: &Path,
+) -> Result<Output, Box<dyn Error>> {
+    let mut command = test_bin::get_test_bin("search-utils");
+    command.arg("normalize-index");
+    if let Some(keys) = keys {
+        command.arg("--keys").arg(keys);
+    }
+    Ok(command.arg(index).arg(so_tree).arg("-").output()?)
+}
+
+fn normalized_ids(output: &Output) -> Result<Vec<Value>, Box<dyn Error>> {
+    Ok(output
+        .jsonl()?
+        .into_iter()
+        .map(|entry| {
+            json!({
+                "urs_taxid": entry["urs_taxid"],
+                "urs": entry["urs"],
+                "taxid": entry["taxid"],
+                "rna_type": entry["rna_type"],
+                "so_rna_type_tree": entry["so_rna_type_tree"],
+            })
+        })
+        .collect())
+}
+
+#[test]
+fn can_normalize_keys_from_an_index() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let index = example_index(dir.path())?;
+    let so_tree = temp_jsonl(vec![
+        json!({
+            "so_rna_type": "SO:0000650",
+            "so_term_tree": [["SO:0000655", "ncRNA"], ["SO:0000650", "SSU_rRNA"]],
+        }),
+        json!({
+            "so_rna_type": "SO:0000253",
+            "so_term_tree": [["SO:0000655", "ncRNA"], ["SO:0000253", "tRNA"]],
+        }),
+    ])?;
+    let keys = temp_file_with(vec!["URS0000614226_291828", "", "URS0000000001_9606"])?;
+
+    let result = normalize_index(Some(keys.path()), &index, so_tree.path())?;
+    assert_eq!(String::from_utf8_lossy(&result.stderr), "");
+    assert!(result.status.success());
+    assert_eq!(normalized_ids(&result)?, vec![
+        json!({
+            "urs_taxid": "URS0000614226_291828",
+            "urs": "URS0000614226",
+            "taxid": 291828,
+            "rna_type": "rRNA",
+            "so_rna_type_tree": [["SO:0000655", "ncRNA"], ["SO:0000650", "SSU_rRNA"]],
+        }),
+        json!({
+            "urs_taxid": "URS0000000001_9606",
+            "urs": "URS0000000001",
+            "taxid": 9606,
+            "rna_type": "tRNA",
+            "so_rna_type_tree": [["SO:0000655", "ncRNA"], ["SO:0000253", "tRNA"]],
+        }),
+    ]);
+
+    let result = normalize_index(None, &index, so_tree.path())?;
+    assert!(result.status.success());
+    assert_eq!(result.jsonl()?.len(), 2);
+
+    let missing = temp_file_with(vec!["URS0000000002_9606"])?;
+    let result = normalize_index(Some(missing.path()), &index, so_tree.path())?;
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("No data found for key"));
+    Ok(())
+}
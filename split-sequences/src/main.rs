@@ -1,5 +1,4 @@
 use std::fs;
-use std::io;
 use std::path::PathBuf;
 
 extern crate log;
@@ -52,12 +51,15 @@ fn main() -> Result<()> {
 
     for (key, records) in records.into_iter() {
         let filename = utils::filename(key, &opt.output, &opt.filename);
-        let writer = fs::File::create(filename)?;
-        let writer = io::BufWriter::new(writer);
-        let mut writer = fasta::Writer::new(writer);
-        for record in records {
-            writer.write_record(&record)?;
+        let mut output = rnc_utils::buf_writer(&filename)?;
+        {
+            let mut writer = fasta::Writer::new(&mut output);
+            for record in records {
+                writer.write_record(&record)?;
+            }
+            writer.flush()?;
         }
+        output.finish()?;
     }
 
     Ok(())
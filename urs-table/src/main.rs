@@ -0,0 +1,135 @@
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use structopt::StructOpt;
+
+use anyhow::{
+    Context,
+    Result,
+};
+
+use rnc_core::{
+    containers::{
+        bloom::BloomStore,
+        lines,
+        table,
+        table::{
+            UrsTable,
+            UrsTaxidTable,
+        },
+        urs::UrsStore,
+    },
+    urs::Urs,
+    urs_taxid::UrsTaxid,
+};
+
+/// Build the sorted tables of URS or URS_taxids which active-sequences and expand-urs can load
+/// instead of a text file of ids. A table only needs to be built once, and can then be loaded
+/// instantly as many times as needed. This can also build Bloom filters of URS, which
+/// active-sequences can use to skip most inactive sequences cheaply.
+#[derive(Debug, StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+enum Opt {
+    /// Build a table from a file where each line is a URS.
+    Urs {
+        /// A file where each line is a URS, '-' means stdin. This may contain duplicates.
+        #[structopt(parse(from_os_str))]
+        filename: PathBuf,
+
+        /// File to write the table to. This is always written uncompressed, as tables are
+        /// memory mapped when loaded.
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+    },
+
+    /// Build a table from a file where each line is a urs_taxid.
+    UrsTaxids {
+        /// A file where each line is a urs_taxid, '-' means stdin. This may contain duplicates.
+        #[structopt(parse(from_os_str))]
+        filename: PathBuf,
+
+        /// File to write the table to. This is always written uncompressed, as tables are
+        /// memory mapped when loaded.
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+    },
+
+    /// Build a Bloom filter from a file where each line is a URS. The filter is much smaller
+    /// than a table, but will also claim to contain some URS which were not in the file.
+    Bloom {
+        /// The rate of false positives the filter should have.
+        #[structopt(short, long, default_value = "0.01")]
+        false_positive_rate: f64,
+
+        /// A file where each line is a URS, '-' means stdin. This may contain duplicates.
+        #[structopt(parse(from_os_str))]
+        filename: PathBuf,
+
+        /// File to write the filter to. This is always written uncompressed.
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+    },
+}
+
+/// Read every id in the file with the given parser, the ids may be in any case.
+fn read_ids<T, E, P>(path: &Path, parse: P) -> Result<Vec<T>>
+where
+    E: std::error::Error + Send + Sync + 'static,
+    P: Fn(&str) -> std::result::Result<T, E>,
+{
+    let mut ids = Vec::new();
+    lines::each_parsed(path, parse, |id| ids.push(id))?;
+    Ok(ids)
+}
+
+/// Create the output file. This does not go through rnc_utils, which would compress the output
+/// based on the extension and so make it impossible to map.
+fn create(path: &Path) -> Result<BufWriter<File>> {
+    let file = File::create(path).with_context(|| format!("Could not create {}", path.display()))?;
+    Ok(BufWriter::new(file))
+}
+
+fn main() -> Result<()> {
+    let opt = Opt::from_args();
+    // Loading a table does not check the order of its entries, so check it once here.
+    match opt {
+        Opt::Urs {
+            filename,
+            output,
+        } => {
+            let ids = read_ids(&filename, Urs::parse_any_case)?;
+            table::write_urs(create(&output)?, ids)?;
+            UrsTable::open(&output)?.verify()?;
+        },
+        Opt::UrsTaxids {
+            filename,
+            output,
+        } => {
+            let ids = read_ids(&filename, UrsTaxid::parse_any_case)?;
+            table::write_urs_taxids(create(&output)?, ids)?;
+            UrsTaxidTable::open(&output)?.verify()?;
+        },
+        Opt::Bloom {
+            false_positive_rate,
+            filename,
+            output,
+        } => {
+            // Duplicates would make the filter larger than the requested rate needs.
+            let mut ids = read_ids(&filename, Urs::parse_any_case)?;
+            ids.sort_unstable();
+            ids.dedup();
+            let mut filter = BloomStore::new(ids.len(), false_positive_rate)?;
+            for urs in &ids {
+                filter.add(urs);
+            }
+            filter.write_to(create(&output)?)?;
+        },
+    }
+    Ok(())
+}
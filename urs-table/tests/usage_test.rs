@@ -0,0 +1,98 @@
+use std::{
+    error::Error,
+    path::Path,
+    process::Output,
+};
+
+use tempfile::{
+    Builder,
+    NamedTempFile,
+};
+
+use rnc_core::containers::{
+    bloom::BloomStore,
+    table::{
+        UrsTable,
+        UrsTaxidTable,
+    },
+    urs::UrsStore,
+};
+
+use rnc_test_utils::temp_file_with;
+
+fn build(kind: &str, filename: &Path, output: &Path) -> std::io::Result<Output> {
+    test_bin::get_test_bin("urs-table").arg(kind).arg(filename).arg(output).output()
+}
+
+#[test]
+fn can_build_urs_taxid_tables() -> Result<(), Box<dyn Error>> {
+    let ids = temp_file_with(vec![
+        "URS0000762A36_9606",
+        "URS0000762A36_562",
+    ])?;
+    // Tables are never compressed, whatever the name of the output.
+    let output = Builder::new().suffix(".gz").tempfile()?;
+    let result = build("urs-taxids", ids.path(), output.path())?;
+    assert_eq!(String::from_utf8_lossy(&result.stderr), "");
+    assert_eq!(result.status.success(), true);
+
+    let table = UrsTaxidTable::open(output.path())?;
+    assert_eq!(table.len(), 2);
+    assert_eq!(table.contains(&"URS0000762A36_562".parse()?), true);
+    assert_eq!(table.contains(&"URS0000762A36_10090".parse()?), false);
+    Ok(())
+}
+
+#[test]
+fn can_build_urs_tables() -> Result<(), Box<dyn Error>> {
+    let ids = temp_file_with(vec![
+        "URS0000762A36",
+        "URS0000000001",
+        "URS0000762A36",
+    ])?;
+    let output = NamedTempFile::new()?;
+    let result = build("urs", ids.path(), output.path())?;
+    assert_eq!(String::from_utf8_lossy(&result.stderr), "");
+    assert_eq!(result.status.success(), true);
+
+    let table = UrsTable::open(output.path())?;
+    assert_eq!(table.contains(&"URS0000762A36".parse()?), true);
+    assert_eq!(table.contains(&"URS0000000002".parse()?), false);
+    Ok(())
+}
+
+#[test]
+fn can_build_bloom_filters() -> Result<(), Box<dyn Error>> {
+    let ids = temp_file_with(vec![
+        "URS0000762A36",
+        "URS0000000001",
+    ])?;
+    let output = NamedTempFile::new()?;
+    let result = test_bin::get_test_bin("urs-table")
+        .arg("bloom")
+        .arg("--false-positive-rate")
+        .arg("0.001")
+        .arg(ids.path())
+        .arg(output.path())
+        .output()?;
+    assert_eq!(String::from_utf8_lossy(&result.stderr), "");
+    assert_eq!(result.status.success(), true);
+
+    let filter = BloomStore::read_from(output.as_file())?;
+    assert_eq!(filter.contains(&"URS0000762A36".parse()?), true);
+    assert_eq!(filter.contains(&"URS0000000001".parse()?), true);
+    Ok(())
+}
+
+#[test]
+fn fails_on_invalid_ids() -> Result<(), Box<dyn Error>> {
+    let ids = temp_file_with(vec![
+        "URS0000762A36_9606",
+        "not-an-id",
+    ])?;
+    let output = NamedTempFile::new()?;
+    let result = build("urs-taxids", ids.path(), output.path())?;
+    assert_eq!(result.status.success(), false);
+    assert!(String::from_utf8_lossy(&result.stderr).contains("not-an-id"));
+    Ok(())
+}